@@ -17,7 +17,10 @@ use std::path::Path;
 use tables::{SledEventTree, SledEventTreeVec};
 
 #[cfg(feature = "query")]
-use crate::query::reply::SignedReply;
+use crate::query::{
+    mailbox::MailboxMessage,
+    reply::{ReplyPayload, SignedReply},
+};
 
 pub struct SledEventDatabase {
     // "iids" tree
@@ -29,6 +32,8 @@ pub struct SledEventDatabase {
     likely_duplicious_events: SledEventTreeVec<TimestampedEventMessage>,
     // "dels" tree
     duplicitous_events: SledEventTreeVec<TimestampedSignedEventMessage>,
+    // "pwes" tree
+    partially_witnessed_events: SledEventTreeVec<TimestampedSignedEventMessage>,
     // "rcts" tree
     receipts_nt: SledEventTreeVec<SignedNontransferableReceipt>,
     // "ures" tree
@@ -43,6 +48,18 @@ pub struct SledEventDatabase {
 
     #[cfg(feature = "query")]
     escrowed_replys: SledEventTreeVec<SignedReply>,
+
+    // "locs" tree
+    #[cfg(feature = "query")]
+    loc_schemes: SledEventTreeVec<SignedReply>,
+
+    // "ends" tree
+    #[cfg(feature = "query")]
+    end_roles: SledEventTreeVec<SignedReply>,
+
+    // "mbxs" tree
+    #[cfg(feature = "query")]
+    mailbox: SledEventTreeVec<MailboxMessage>,
 }
 
 impl SledEventDatabase {
@@ -60,10 +77,17 @@ impl SledEventDatabase {
             key_event_logs: SledEventTreeVec::new(db.open_tree(b"kels")?),
             likely_duplicious_events: SledEventTreeVec::new(db.open_tree(b"ldes")?),
             duplicitous_events: SledEventTreeVec::new(db.open_tree(b"dels")?),
+            partially_witnessed_events: SledEventTreeVec::new(db.open_tree(b"pwes")?),
             #[cfg(feature = "query")]
             accepted_rpy: SledEventTreeVec::new(db.open_tree(b"knas")?),
             #[cfg(feature = "query")]
             escrowed_replys: SledEventTreeVec::new(db.open_tree(b"knes")?),
+            #[cfg(feature = "query")]
+            loc_schemes: SledEventTreeVec::new(db.open_tree(b"locs")?),
+            #[cfg(feature = "query")]
+            end_roles: SledEventTreeVec::new(db.open_tree(b"ends")?),
+            #[cfg(feature = "query")]
+            mailbox: SledEventTreeVec::new(db.open_tree(b"mbxs")?),
         })
     }
 
@@ -189,6 +213,32 @@ impl SledEventDatabase {
             .remove(self.identifiers.designated_key(id), receipt)
     }
 
+    pub fn add_partially_witnessed_event(
+        &self,
+        event: SignedEventMessage,
+        id: &IdentifierPrefix,
+    ) -> Result<(), Error> {
+        self.partially_witnessed_events
+            .push(self.identifiers.designated_key(id), event.into())
+    }
+
+    pub fn get_partially_witnessed_events(
+        &self,
+        id: &IdentifierPrefix,
+    ) -> Option<impl DoubleEndedIterator<Item = TimestampedSignedEventMessage>> {
+        self.partially_witnessed_events
+            .iter_values(self.identifiers.designated_key(id))
+    }
+
+    pub fn remove_partially_witnessed_event(
+        &self,
+        id: &IdentifierPrefix,
+        event: &SignedEventMessage,
+    ) -> Result<(), Error> {
+        self.partially_witnessed_events
+            .remove(self.identifiers.designated_key(id), &event.into())
+    }
+
     pub fn add_likely_duplicious_event(
         &self,
         event: EventMessage<KeyEvent>,
@@ -295,4 +345,81 @@ impl SledEventDatabase {
     pub fn get_all_escrowed_replys(&self) -> Option<impl DoubleEndedIterator<Item = SignedReply>> {
         self.escrowed_replys.get_all()
     }
+
+    /// Saves `/loc/scheme` reply of endpoint `eid`, replacing previous one
+    /// for the same scheme.
+    #[cfg(feature = "query")]
+    pub fn update_loc_scheme(&self, rpy: SignedReply, eid: &IdentifierPrefix) -> Result<(), Error> {
+        let scheme = |rpy: &SignedReply| match rpy.reply.event.get_reply_data() {
+            ReplyPayload::LocScheme(loc_scheme) => Some(loc_scheme.scheme),
+            _ => None,
+        };
+        let key = self.identifiers.designated_key(eid);
+        let filtered = self
+            .loc_schemes
+            .iter_values(key)
+            .into_iter()
+            .flatten()
+            .filter(|saved| scheme(saved) != scheme(&rpy))
+            .chain(Some(rpy.clone()))
+            .collect();
+        self.loc_schemes.put(key, filtered)
+    }
+
+    #[cfg(feature = "query")]
+    pub fn get_loc_schemes(
+        &self,
+        eid: &IdentifierPrefix,
+    ) -> Option<impl DoubleEndedIterator<Item = SignedReply>> {
+        self.loc_schemes
+            .iter_values(self.identifiers.designated_key(eid))
+    }
+
+    /// Saves `/end/role/add` or `/end/role/cut` reply of controller `cid`,
+    /// replacing previous one for the same role and endpoint.
+    #[cfg(feature = "query")]
+    pub fn update_end_role(&self, rpy: SignedReply, cid: &IdentifierPrefix) -> Result<(), Error> {
+        let role = |rpy: &SignedReply| match rpy.reply.event.get_reply_data() {
+            ReplyPayload::EndRole(end_role) => Some((end_role.role, end_role.eid)),
+            _ => None,
+        };
+        let key = self.identifiers.designated_key(cid);
+        let filtered = self
+            .end_roles
+            .iter_values(key)
+            .into_iter()
+            .flatten()
+            .filter(|saved| role(saved) != role(&rpy))
+            .chain(Some(rpy.clone()))
+            .collect();
+        self.end_roles.put(key, filtered)
+    }
+
+    #[cfg(feature = "query")]
+    pub fn get_end_roles(
+        &self,
+        cid: &IdentifierPrefix,
+    ) -> Option<impl DoubleEndedIterator<Item = SignedReply>> {
+        self.end_roles
+            .iter_values(self.identifiers.designated_key(cid))
+    }
+
+    #[cfg(feature = "query")]
+    pub fn add_mailbox_message(
+        &self,
+        message: MailboxMessage,
+        recipient: &IdentifierPrefix,
+    ) -> Result<(), Error> {
+        self.mailbox
+            .push(self.identifiers.designated_key(recipient), message)
+    }
+
+    #[cfg(feature = "query")]
+    pub fn get_mailbox_messages(
+        &self,
+        recipient: &IdentifierPrefix,
+    ) -> Option<impl DoubleEndedIterator<Item = MailboxMessage>> {
+        self.mailbox
+            .iter_values(self.identifiers.designated_key(recipient))
+    }
 }
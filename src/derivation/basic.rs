@@ -1,4 +1,4 @@
-use super::DerivationCode;
+use super::{self_signing::SelfSigning, DerivationCode};
 use crate::{error::Error, keys::PublicKey, prefix::BasicPrefix};
 use core::str::FromStr;
 use serde::{Deserialize, Serialize};
@@ -22,6 +22,28 @@ impl Basic {
     pub fn derive(&self, public_key: PublicKey) -> BasicPrefix {
         BasicPrefix::new(*self, public_key)
     }
+
+    /// Non-transferable keys can't be rotated, so identifier made of such
+    /// key commits to no next keys.
+    pub fn is_transferable(&self) -> bool {
+        !matches!(
+            self,
+            Self::Ed25519NT | Self::ECDSAsecp256k1NT | Self::Ed448NT
+        )
+    }
+
+    /// Derivation of signatures made with key of this derivation. Key
+    /// agreement keys can't sign.
+    pub fn self_signing(&self) -> Option<SelfSigning> {
+        match self {
+            Self::Ed25519NT | Self::Ed25519 => Some(SelfSigning::Ed25519Sha512),
+            Self::ECDSAsecp256k1NT | Self::ECDSAsecp256k1 => {
+                Some(SelfSigning::ECDSAsecp256k1Sha256)
+            }
+            Self::Ed448NT | Self::Ed448 => Some(SelfSigning::Ed448),
+            Self::X25519 | Self::X448 => None,
+        }
+    }
 }
 
 impl DerivationCode for Basic {
@@ -50,7 +72,7 @@ impl DerivationCode for Basic {
         match self {
             Self::Ed25519NT | Self::Ed25519 | Self::X25519 => 43,
             Self::X448 => 75,
-            Self::ECDSAsecp256k1NT | Self::ECDSAsecp256k1 => 47,
+            Self::ECDSAsecp256k1NT | Self::ECDSAsecp256k1 => 44,
             Self::Ed448NT | Self::Ed448 => 76,
         }
     }
@@ -49,9 +49,15 @@ pub enum Error {
     #[error("Error while applying event: duplicate event")]
     EventDuplicateError,
 
+    #[error("Error while applying event: identifier is abandoned")]
+    AbandonedIdentifierError,
+
     #[error("Not enough signatures while verifying")]
     NotEnoughSigsError,
 
+    #[error("Not enough receipts, event escrowed")]
+    NotEnoughReceiptsError,
+
     #[error("Signature verification failed")]
     SignatureVerificationError,
 
@@ -111,4 +117,22 @@ pub enum Error {
 
     #[error("Public Key Error: {0}")]
     PublicKeyError(String),
+
+    #[error("Key derivation error: {0}")]
+    KeyDerivationError(String),
+
+    #[error("Keystore error: {0}")]
+    KeyStoreError(String),
+
+    #[cfg(feature = "async")]
+    #[error("Remote signer error: {0}")]
+    RemoteSignerError(String),
+
+    #[cfg(feature = "async")]
+    #[error("Transport error: {0}")]
+    TransportError(String),
+
+    #[cfg(feature = "pkcs11")]
+    #[error(transparent)]
+    Pkcs11Error(#[from] cryptoki::error::Error),
 }
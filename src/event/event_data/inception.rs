@@ -1,5 +1,5 @@
 use super::{
-    super::sections::{InceptionWitnessConfig, KeyConfig},
+    super::sections::{configuration::ConfigurationTrait, InceptionWitnessConfig, KeyConfig},
     EventData,
 };
 use crate::{
@@ -10,7 +10,7 @@ use crate::{
         dummy_event::DummyInceptionEvent, key_event_message::KeyEvent,
         serialization_info::SerializationFormats, EventMessage, SaidEvent,
     },
-    prefix::IdentifierPrefix,
+    prefix::{IdentifierPrefix, SelfSigningPrefix},
     state::{EventSemantics, IdentifierState, LastEstablishmentData},
 };
 use serde::{Deserialize, Serialize};
@@ -27,7 +27,7 @@ pub struct InceptionEvent {
     pub witness_config: InceptionWitnessConfig,
 
     #[serde(rename = "c")]
-    pub inception_configuration: Vec<String>,
+    pub inception_configuration: Vec<ConfigurationTrait>,
 
     #[serde(rename = "a")]
     pub data: Vec<Seal>,
@@ -37,7 +37,7 @@ impl InceptionEvent {
     pub fn new(
         key_config: KeyConfig,
         witness_config: Option<InceptionWitnessConfig>,
-        inception_config: Option<Vec<String>>,
+        inception_config: Option<Vec<ConfigurationTrait>>,
     ) -> Self {
         Self {
             key_config,
@@ -70,10 +70,49 @@ impl InceptionEvent {
             event: SaidEvent::new(digest, event),
         })
     }
+
+    /// Self Signing Data
+    ///
+    /// Serialized inception data with placeholder prefix. Signature of the
+    /// inception key over it is the prefix of Self Signing Identifier.
+    pub fn self_signing_data(
+        &self,
+        derivation: &SelfAddressing,
+        format: SerializationFormats,
+    ) -> Result<Vec<u8>, Error> {
+        DummyInceptionEvent::dummy_inception_data(self.clone(), derivation, format)?.serialize()
+    }
+
+    /// Incept Self Signing
+    ///
+    /// Creates an EventMessage of Self Signing Identifier, with `signature`
+    /// made over `self_signing_data` of the inception data.
+    pub fn incept_self_signing(
+        self,
+        signature: SelfSigningPrefix,
+        derivation: SelfAddressing,
+        format: SerializationFormats,
+    ) -> Result<EventMessage<KeyEvent>, Error> {
+        Event::new(
+            IdentifierPrefix::SelfSigning(signature),
+            0,
+            EventData::Icp(self),
+        )
+        .to_message(format, &derivation)
+    }
 }
 
 impl EventSemantics for InceptionEvent {
     fn apply_to(&self, state: IdentifierState) -> Result<IdentifierState, Error> {
+        if self
+            .inception_configuration
+            .contains(&ConfigurationTrait::NoBackers)
+            && !self.witness_config.initial_witnesses.is_empty()
+        {
+            return Err(Error::SemanticError(
+                "Witnesses of identifier without backers".into(),
+            ));
+        }
         let last_est = LastEstablishmentData {
             sn: state.sn,
             digest: state.last_event_digest.clone(),
@@ -84,6 +123,7 @@ impl EventSemantics for InceptionEvent {
             current: self.key_config.clone(),
             witnesses: self.witness_config.initial_witnesses.clone(),
             tally: self.witness_config.tally,
+            config: self.inception_configuration.clone(),
             last_est,
             ..state
         })
@@ -1,4 +1,4 @@
-use super::super::sections::seal::*;
+use super::super::sections::{configuration::ConfigurationTrait, seal::*};
 use crate::error::Error;
 use crate::prefix::SelfAddressingPrefix;
 use crate::state::{EventSemantics, IdentifierState};
@@ -24,6 +24,14 @@ impl InteractionEvent {
 
 impl EventSemantics for InteractionEvent {
     fn apply_to(&self, state: IdentifierState) -> Result<IdentifierState, Error> {
+        if state
+            .config
+            .contains(&ConfigurationTrait::EstablishmentOnly)
+        {
+            return Err(Error::SemanticError(
+                "Interaction event of establishment only identifier".into(),
+            ));
+        }
         Ok(IdentifierState { ..state })
     }
 }
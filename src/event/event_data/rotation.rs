@@ -1,7 +1,9 @@
-use super::super::sections::{seal::*, KeyConfig, WitnessConfig};
+use super::super::sections::{
+    configuration::ConfigurationTrait, seal::*, KeyConfig, WitnessConfig,
+};
 use crate::{
     error::Error,
-    prefix::{BasicPrefix, SelfAddressingPrefix},
+    prefix::SelfAddressingPrefix,
     state::{EventSemantics, IdentifierState, LastEstablishmentData},
 };
 use serde::{Deserialize, Serialize};
@@ -26,25 +28,20 @@ pub struct RotationEvent {
 
 impl EventSemantics for RotationEvent {
     fn apply_to(&self, state: IdentifierState) -> Result<IdentifierState, Error> {
+        if state.config.contains(&ConfigurationTrait::NoBackers)
+            && !self.witness_config.graft.is_empty()
+        {
+            return Err(Error::SemanticError(
+                "Witnesses of identifier without backers".into(),
+            ));
+        }
         if state.current.verify_next(&self.key_config) {
-            // witness rotation processing
-            let witnesses =
-                if !self.witness_config.prune.is_empty() || !self.witness_config.graft.is_empty() {
-                    let mut prunned = state
-                        .witnesses
-                        .into_iter()
-                        .filter(|e| !self.witness_config.prune.contains(e))
-                        .collect::<Vec<BasicPrefix>>();
-                    prunned.append(&mut self.witness_config.graft.clone());
-                    prunned
-                } else {
-                    state.witnesses.clone()
-                };
+            let witnesses = self.witness_config.rotate_witnesses(&state.witnesses)?;
             let last_est = LastEstablishmentData {
                 sn: state.sn,
                 digest: state.last_event_digest.clone(),
-                br: self.witness_config.graft.clone(),
-                ba: self.witness_config.prune.clone(),
+                br: self.witness_config.prune.clone(),
+                ba: self.witness_config.graft.clone(),
             };
 
             Ok(IdentifierState {
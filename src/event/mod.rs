@@ -76,6 +76,13 @@ impl EventSemantics for Event {
                 // TODO recovery will break this rule when we implement it
                 } else if self.sn < state.sn + 1 {
                     return Err(Error::EventDuplicateError);
+                } else if matches!(&state.prefix, IdentifierPrefix::Basic(bp) if !bp.is_transferable())
+                {
+                    return Err(Error::SemanticError(
+                        "Non-transferable identifier can't have further events".into(),
+                    ));
+                } else if state.is_abandoned() {
+                    return Err(Error::AbandonedIdentifierError);
                 } else if self.sn > state.sn + 1 {
                     return Err(Error::EventOutOfOrderError);
                 }
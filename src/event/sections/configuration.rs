@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+
+/// Configuration Trait
+///
+/// Trait of identifier declared in `c` field of inception event, which
+/// restricts events allowed in its KEL. Traits we don't enforce are kept
+/// as is, so KELs of other implementations still parse.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(from = "String", into = "String")]
+pub enum ConfigurationTrait {
+    /// Only establishment events are allowed, so no `ixn`
+    EstablishmentOnly,
+    /// Identifier can't act as delegator
+    DoNotDelegate,
+    /// Identifier can't have witnesses
+    NoBackers,
+    /// Trait unknown to us, e.g. keripy's `RB` or `DID`
+    Other(String),
+}
+
+impl From<String> for ConfigurationTrait {
+    fn from(code: String) -> Self {
+        match code.as_str() {
+            "EO" => Self::EstablishmentOnly,
+            "DND" => Self::DoNotDelegate,
+            "NB" => Self::NoBackers,
+            _ => Self::Other(code),
+        }
+    }
+}
+
+impl From<ConfigurationTrait> for String {
+    fn from(config: ConfigurationTrait) -> Self {
+        match config {
+            ConfigurationTrait::EstablishmentOnly => "EO".into(),
+            ConfigurationTrait::DoNotDelegate => "DND".into(),
+            ConfigurationTrait::NoBackers => "NB".into(),
+            ConfigurationTrait::Other(code) => code,
+        }
+    }
+}
+
+#[test]
+fn test_configuration_serialization() {
+    let traits = vec![
+        ConfigurationTrait::EstablishmentOnly,
+        ConfigurationTrait::DoNotDelegate,
+        ConfigurationTrait::NoBackers,
+    ];
+    let serialized = serde_json::to_string(&traits).unwrap();
+    assert_eq!(serialized, r#"["EO","DND","NB"]"#);
+    assert_eq!(
+        serde_json::from_str::<Vec<ConfigurationTrait>>(&serialized).unwrap(),
+        traits
+    );
+
+    // unknown traits round-trip
+    let foreign = r#"["RB","DID"]"#;
+    let traits = serde_json::from_str::<Vec<ConfigurationTrait>>(foreign).unwrap();
+    assert_eq!(traits[0], ConfigurationTrait::Other("RB".into()));
+    assert_eq!(serde_json::to_string(&traits).unwrap(), foreign);
+}
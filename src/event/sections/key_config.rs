@@ -122,7 +122,7 @@ pub fn nxt_commitment(
     )
 }
 
-mod empty_string_as_none {
+pub(crate) mod empty_string_as_none {
     use serde::{de::IntoDeserializer, Deserialize, Deserializer, Serializer};
 
     pub fn deserialize<'d, D, T>(de: D) -> Result<Option<T>, D::Error>
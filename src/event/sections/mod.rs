@@ -1,7 +1,11 @@
-use crate::prefix::BasicPrefix;
+use crate::{
+    error::Error,
+    prefix::{BasicPrefix, Prefix},
+};
 use serde::{Deserialize, Serialize};
 use serde_hex::{Compact, SerHex};
 
+pub mod configuration;
 pub mod key_config;
 pub mod seal;
 pub mod threshold;
@@ -19,6 +23,47 @@ pub struct WitnessConfig {
     pub graft: Vec<BasicPrefix>,
 }
 
+impl WitnessConfig {
+    /// Rotate Witnesses
+    ///
+    /// Applies pruning and grafting to the current witness list. Fails if
+    /// a witness is both pruned and grafted, a pruned witness is not in the
+    /// list, a grafted one already is, or the tally exceeds the number of
+    /// resulting witnesses.
+    pub fn rotate_witnesses(&self, witnesses: &[BasicPrefix]) -> Result<Vec<BasicPrefix>, Error> {
+        let mut rotated = witnesses.to_vec();
+        for (i, w) in self.prune.iter().enumerate() {
+            if self.prune[..i].contains(w) || self.graft.contains(w) {
+                return Err(Error::SemanticError(format!(
+                    "Witness {} pruned more than once or also grafted",
+                    w.to_str()
+                )));
+            }
+            let position = rotated.iter().position(|e| e == w).ok_or_else(|| {
+                Error::SemanticError(format!("Pruned witness {} not present", w.to_str()))
+            })?;
+            rotated.remove(position);
+        }
+        for w in &self.graft {
+            if rotated.contains(w) {
+                return Err(Error::SemanticError(format!(
+                    "Grafted witness {} already present",
+                    w.to_str()
+                )));
+            }
+            rotated.push(w.clone());
+        }
+        if self.tally > rotated.len() as u64 {
+            return Err(Error::SemanticError(format!(
+                "Witness tally {} exceeds number of witnesses {}",
+                self.tally,
+                rotated.len()
+            )));
+        }
+        Ok(rotated)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
 pub struct InceptionWitnessConfig {
     #[serde(rename = "bt", with = "SerHex::<Compact>")]
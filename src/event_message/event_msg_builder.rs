@@ -7,7 +7,9 @@ use crate::{
             delegated::DelegatedInceptionEvent, interaction::InteractionEvent,
             rotation::RotationEvent,
         },
-        sections::{threshold::SignatureThreshold, WitnessConfig},
+        sections::{
+            configuration::ConfigurationTrait, threshold::SignatureThreshold, WitnessConfig,
+        },
         SerializationFormats,
     },
     event::{
@@ -41,6 +43,7 @@ pub struct EventMsgBuilder {
     witnesses: Vec<BasicPrefix>,
     witness_to_add: Vec<BasicPrefix>,
     witness_to_remove: Vec<BasicPrefix>,
+    configuration: Vec<ConfigurationTrait>,
     format: SerializationFormats,
     derivation: SelfAddressing,
 }
@@ -68,6 +71,7 @@ impl EventMsgBuilder {
             witnesses: vec![],
             witness_to_add: vec![],
             witness_to_remove: vec![],
+            configuration: vec![],
             format: SerializationFormats::JSON,
             derivation: SelfAddressing::Blake3_256,
         }
@@ -103,6 +107,14 @@ impl EventMsgBuilder {
         EventMsgBuilder { ..self }
     }
 
+    pub fn with_format(self, format: SerializationFormats) -> Self {
+        EventMsgBuilder { format, ..self }
+    }
+
+    pub fn with_derivation(self, derivation: SelfAddressing) -> Self {
+        EventMsgBuilder { derivation, ..self }
+    }
+
     pub fn with_delegator(self, delegator: &IdentifierPrefix) -> Self {
         EventMsgBuilder {
             delegator: delegator.clone(),
@@ -131,6 +143,13 @@ impl EventMsgBuilder {
         }
     }
 
+    pub fn with_witness_threshold(self, witness_threshold: u64) -> Self {
+        EventMsgBuilder {
+            witness_threshold,
+            ..self
+        }
+    }
+
     pub fn with_witness_to_add(self, witness_to_add: &[BasicPrefix]) -> Self {
         EventMsgBuilder {
             witness_to_add: witness_to_add.to_vec(),
@@ -145,10 +164,25 @@ impl EventMsgBuilder {
         }
     }
 
+    pub fn with_configuration(self, configuration: &[ConfigurationTrait]) -> Self {
+        EventMsgBuilder {
+            configuration: configuration.to_vec(),
+            ..self
+        }
+    }
+
     pub fn build(self) -> Result<EventMessage<KeyEvent>, Error> {
-        let next_key_hash =
-            nxt_commitment(&self.next_key_threshold, &self.next_keys, &self.derivation);
-        let key_config = KeyConfig::new(self.keys, Some(next_key_hash), Some(self.key_threshold));
+        // no next keys means no commitment, which ends the KEL
+        let next_key_hash = if self.next_keys.is_empty() {
+            None
+        } else {
+            Some(nxt_commitment(
+                &self.next_key_threshold,
+                &self.next_keys,
+                &self.derivation,
+            ))
+        };
+        let key_config = KeyConfig::new(self.keys, next_key_hash, Some(self.key_threshold));
         let prefix = if self.prefix == IdentifierPrefix::default() {
             if key_config.public_keys.len() == 1 {
                 IdentifierPrefix::Basic(key_config.public_keys[0].clone())
@@ -169,7 +203,7 @@ impl EventMsgBuilder {
                         tally: self.witness_threshold,
                         initial_witnesses: self.witnesses,
                     },
-                    inception_configuration: vec![],
+                    inception_configuration: self.configuration,
                     data: vec![],
                 };
 
@@ -210,8 +244,11 @@ impl EventMsgBuilder {
             EventTypeTag::Dip => {
                 let icp_data = InceptionEvent {
                     key_config,
-                    witness_config: InceptionWitnessConfig::default(),
-                    inception_configuration: vec![],
+                    witness_config: InceptionWitnessConfig {
+                        tally: self.witness_threshold,
+                        initial_witnesses: self.witnesses,
+                    },
+                    inception_configuration: self.configuration,
                     data: vec![],
                 };
                 DelegatedInceptionEvent {
@@ -169,15 +169,30 @@ pub fn verify_identifier_binding(icp_event: &EventMessage<KeyEvent>) -> Result<b
     match event_data {
         EventData::Icp(icp) => match &icp_event.event.get_prefix() {
             IdentifierPrefix::Basic(bp) => Ok(icp.key_config.public_keys.len() == 1
-                && bp == icp.key_config.public_keys.first().unwrap()),
+                && bp == icp.key_config.public_keys.first().unwrap()
+                // non-transferable identifier can't commit to next keys
+                // or rely on witnesses
+                && (bp.is_transferable()
+                    || (icp.key_config.threshold_key_digest.is_none()
+                        && icp.witness_config.initial_witnesses.is_empty()))),
             IdentifierPrefix::SelfAddressing(sap) => {
                 Ok(icp_event.check_digest(sap)? && icp_event.get_digest().eq(sap))
             }
-            IdentifierPrefix::SelfSigning(_ssp) => todo!(),
+            IdentifierPrefix::SelfSigning(ssp) => match icp.key_config.public_keys.as_slice() {
+                [key] => key.verify(
+                    &icp.self_signing_data(
+                        &icp_event.get_digest().derivation,
+                        icp_event.serialization_info.kind,
+                    )?,
+                    ssp,
+                ),
+                _ => Ok(false),
+            },
         },
         EventData::Dip(_dip) => match &icp_event.event.get_prefix() {
             IdentifierPrefix::SelfAddressing(sap) => icp_event.check_digest(sap),
-            _ => todo!(),
+            // delegated identifiers are self-addressing only
+            _ => Ok(false),
         },
         _ => Err(Error::SemanticError("Not an ICP or DIP event".into())),
     }
@@ -36,6 +36,8 @@ pub enum EventTypeTag {
     Rpy,
     #[cfg(feature = "query")]
     Qry,
+    #[cfg(feature = "query")]
+    Exn,
 }
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct SaidEvent<D> {
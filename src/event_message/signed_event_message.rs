@@ -16,7 +16,7 @@ use crate::{
 };
 
 #[cfg(feature = "query")]
-use crate::query::{query::SignedQuery, reply::SignedReply};
+use crate::query::{exchange::SignedExchange, query::SignedQuery, reply::SignedReply};
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Message {
@@ -29,6 +29,8 @@ pub enum Message {
     KeyStateNotice(SignedReply),
     #[cfg(feature = "query")]
     Query(SignedQuery),
+    #[cfg(feature = "query")]
+    Exchange(SignedExchange),
 }
 
 // KERI serializer should be used to serialize this
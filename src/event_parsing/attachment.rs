@@ -106,7 +106,7 @@ fn seal_signatures(
 pub fn attachment(s: &[u8]) -> nom::IResult<&[u8], Attachment> {
     let (rest, payload_type) = take(2u8)(s)?;
     let payload_type: PayloadType = PayloadType::try_from(
-        std::str::from_utf8(payload_type).map_err(|_e| nom::Err::Failure((s, ErrorKind::IsNot)))?,
+        std::str::from_utf8(payload_type).map_err(|_e| nom::Err::Error((s, ErrorKind::IsNot)))?,
     )
     // Can't parse payload type
     .map_err(|_e| nom::Err::Error((s, ErrorKind::IsNot)))?;
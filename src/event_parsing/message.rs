@@ -11,9 +11,11 @@ use serde::Deserialize;
 use crate::event_message::serialization_info::SerializationInfo;
 
 #[cfg(feature = "query")]
-use crate::event_message::{SaidEvent, Typeable};
+use crate::event_message::{
+    serialization_info::SerializationFormats, EventTypeTag, SaidEvent, Typeable,
+};
 #[cfg(feature = "query")]
-use crate::query::Envelope;
+use crate::query::{Envelope, EnvelopeData};
 use crate::{
     event::{receipt::Receipt, EventMessage},
     event_message::{key_event_message::KeyEvent, Digestible},
@@ -63,11 +65,33 @@ pub fn receipt_message(s: &[u8]) -> nom::IResult<&[u8], EventType> {
     message::<Receipt>(s).map(|d| (d.0, EventType::Receipt(d.1)))
 }
 
+/// Returns `t` field of serialized message.
+#[cfg(feature = "query")]
+fn message_type(data: &[u8], format: SerializationFormats) -> Option<EventTypeTag> {
+    #[derive(Deserialize)]
+    struct Tagged {
+        t: EventTypeTag,
+    }
+    match format {
+        SerializationFormats::JSON => serde_json::from_slice::<Tagged>(data).ok(),
+        SerializationFormats::CBOR => serde_cbor::from_slice::<Tagged>(data).ok(),
+        SerializationFormats::MGPK => serde_mgpk::from_slice::<Tagged>(data).ok(),
+    }
+    .map(|tagged| tagged.t)
+}
+
+/// Parses envelope message of type given by `D`. Envelope fields don't
+/// determine the message type, so `t` field is checked.
 #[cfg(feature = "query")]
-fn envelope<'a, D: Serialize + Deserialize<'a> + Typeable>(
+fn envelope<'a, D: Serialize + Deserialize<'a> + Typeable + EnvelopeData>(
     s: &'a [u8],
-) -> nom::IResult<&[u8], EventMessage<SaidEvent<Envelope<D>>>> {
-    message::<SaidEvent<Envelope<D>>>(s).map(|d| (d.0, d.1))
+) -> nom::IResult<&'a [u8], EventMessage<SaidEvent<Envelope<D>>>> {
+    let (rest, event) = message::<SaidEvent<Envelope<D>>>(s)?;
+    let data = &s[..s.len() - rest.len()];
+    match message_type(data, event.serialization_info.kind) {
+        Some(t) if t == event.event.get_type() => Ok((rest, event)),
+        _ => Err(nom::Err::Error((s, ErrorKind::IsNot))),
+    }
 }
 
 #[cfg(feature = "query")]
@@ -84,12 +108,20 @@ pub fn reply_message<'a>(s: &'a [u8]) -> nom::IResult<&[u8], EventType> {
     envelope::<ReplyData>(s).map(|d| (d.0, EventType::Rpy(d.1)))
 }
 
+#[cfg(feature = "query")]
+pub fn exchange_message(s: &[u8]) -> nom::IResult<&[u8], EventType> {
+    use crate::query::exchange::ExchangeData;
+
+    envelope::<ExchangeData>(s).map(|d| (d.0, EventType::Exn(d.1)))
+}
+
 pub fn signed_message(s: &[u8]) -> nom::IResult<&[u8], SignedEventData> {
     #[cfg(feature = "query")]
     let (rest, event) = alt((
         key_event_message,
         reply_message,
         query_message,
+        exchange_message,
         receipt_message,
     ))(s)?;
     #[cfg(not(feature = "query"))]
@@ -233,6 +265,25 @@ fn test_reply() {
     assert!(matches!(event, EventType::Rpy(_)));
 }
 
+#[cfg(feature = "query")]
+#[test]
+fn test_envelope_type() {
+    // reply and exchange differ in `t` field only
+    let rpy = r#"{"v":"KERI10JSON0000ba_","t":"rpy","d":"E_v_Syz2Bhh1WCKx9GBSpU4g9FqqxtSNPI_M2KgMC1yI","dt":"2021-01-01T00:00:00.000000+00:00","r":"/app/status","q":{},"a":{"status":"online"}}"#;
+    let exn = rpy.replace(r#""t":"rpy""#, r#""t":"exn""#);
+
+    assert!(matches!(
+        signed_message(rpy.as_bytes()).unwrap().1.deserialized_event,
+        EventType::Rpy(_)
+    ));
+    assert!(exchange_message(rpy.as_bytes()).is_err());
+    assert!(matches!(
+        signed_message(exn.as_bytes()).unwrap().1.deserialized_event,
+        EventType::Exn(_)
+    ));
+    assert!(reply_message(exn.as_bytes()).is_err());
+}
+
 #[cfg(feature = "query")]
 #[test]
 fn test_signed_qry() {
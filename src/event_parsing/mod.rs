@@ -16,7 +16,8 @@ use crate::prefix::{
 
 #[cfg(feature = "query")]
 use crate::query::{
-    query::QueryEvent,
+    exchange::{ExchangeEvent, SignedExchange},
+    query::{QueryEvent, SignedQuery},
     reply::{ReplyEvent, SignedReply},
 };
 use crate::{error::Error, event::event_data::EventData};
@@ -141,6 +142,8 @@ pub enum EventType {
     Qry(EventMessage<QueryEvent>),
     #[cfg(feature = "query")]
     Rpy(EventMessage<ReplyEvent>),
+    #[cfg(feature = "query")]
+    Exn(EventMessage<ExchangeEvent>),
 }
 
 impl EventType {
@@ -152,6 +155,8 @@ impl EventType {
             EventType::Qry(qry) => qry.serialize(),
             #[cfg(feature = "query")]
             EventType::Rpy(rpy) => rpy.serialize(),
+            #[cfg(feature = "query")]
+            EventType::Exn(exn) => exn.serialize(),
         }
     }
 }
@@ -228,6 +233,34 @@ impl From<SignedReply> for SignedEventData {
     }
 }
 
+#[cfg(feature = "query")]
+impl From<SignedQuery> for SignedEventData {
+    fn from(qry: SignedQuery) -> Self {
+        let attachments = vec![Attachment::LastEstSignaturesGroups(vec![(
+            qry.signer,
+            qry.signatures,
+        )])];
+        SignedEventData {
+            deserialized_event: EventType::Qry(qry.envelope),
+            attachments,
+        }
+    }
+}
+
+#[cfg(feature = "query")]
+impl From<SignedExchange> for SignedEventData {
+    fn from(exn: SignedExchange) -> Self {
+        let attachments = vec![Attachment::SealSignaturesGroups(vec![(
+            exn.signer_seal,
+            exn.signatures,
+        )])];
+        SignedEventData {
+            deserialized_event: EventType::Exn(exn.exchange),
+            attachments,
+        }
+    }
+}
+
 impl TryFrom<SignedEventData> for Message {
     type Error = Error;
 
@@ -239,6 +272,8 @@ impl TryFrom<SignedEventData> for Message {
             EventType::Qry(qry) => signed_query(qry, value.attachments),
             #[cfg(feature = "query")]
             EventType::Rpy(rpy) => signed_reply(rpy, value.attachments),
+            #[cfg(feature = "query")]
+            EventType::Exn(exn) => signed_exchange(exn, value.attachments),
         }
     }
 }
@@ -306,36 +341,57 @@ fn signed_query(
     }
 }
 
+#[cfg(feature = "query")]
+fn signed_exchange(
+    exn: EventMessage<ExchangeEvent>,
+    mut attachments: Vec<Attachment>,
+) -> Result<Message, Error> {
+    match attachments
+        .pop()
+        .ok_or_else(|| Error::SemanticError("Missing attachment".into()))?
+    {
+        Attachment::SealSignaturesGroups(groups) => {
+            let (seal, sigs) = groups
+                .last()
+                .ok_or_else(|| Error::SemanticError("Missing signatures group".into()))?
+                .to_owned();
+            Ok(Message::Exchange(SignedExchange::new(exn, seal, sigs)))
+        }
+        Attachment::Frame(atts) => signed_exchange(exn, atts),
+        _ => {
+            // only transferable identifiers can sign exchange messages
+            Err(Error::SemanticError(
+                "Improper attachments for exchange message".into(),
+            ))
+        }
+    }
+}
+
 fn signed_key_event(
     event_message: EventMessage<KeyEvent>,
     mut attachments: Vec<Attachment>,
 ) -> Result<Message, Error> {
     match event_message.event.get_event_data() {
         EventData::Dip(_) | EventData::Drt(_) => {
-            let (att1, att2) = (
-                attachments
-                    .pop()
-                    .ok_or_else(|| Error::SemanticError("Missing attachment".into()))?,
-                attachments
-                    .pop()
-                    .ok_or_else(|| Error::SemanticError("Missing attachment".into()))?,
-            );
-
-            let (seals, sigs) = match (att1, att2) {
-                (Attachment::SealSourceCouplets(seals), Attachment::AttachedSignatures(sigs)) => {
-                    Ok((seals, sigs))
-                }
-                (Attachment::AttachedSignatures(sigs), Attachment::SealSourceCouplets(seals)) => {
-                    Ok((seals, sigs))
-                }
+            // delegation request, not approved yet, has no source seal
+            let (seals, sigs) = match (attachments.pop(), attachments.pop()) {
+                (
+                    Some(Attachment::SealSourceCouplets(seals)),
+                    Some(Attachment::AttachedSignatures(sigs)),
+                ) => Ok((seals, sigs)),
+                (
+                    Some(Attachment::AttachedSignatures(sigs)),
+                    Some(Attachment::SealSourceCouplets(seals)),
+                ) => Ok((seals, sigs)),
+                (Some(Attachment::AttachedSignatures(sigs)), None) => Ok((vec![], sigs)),
+                (None, _) => Err(Error::SemanticError("Missing attachment".into())),
                 _ => {
                     // Improper attachment type
                     Err(Error::SemanticError("Improper attachment type".into()))
                 }
             }?;
             let delegator_seal = match seals.len() {
-                0 => Err(Error::SemanticError("Missing delegator seal".into())),
-                1 => Ok(seals.first().cloned()),
+                0 | 1 => Ok(seals.first().cloned()),
                 _ => Err(Error::SemanticError("Too many seals".into())),
             };
 
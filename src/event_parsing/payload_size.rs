@@ -139,6 +139,7 @@ impl PayloadType {
             | Self::ME
             | Self::MF
             | Self::MG
+            | Self::MH
             | Self::MU
             | Self::MV
             | Self::MW
@@ -180,6 +181,7 @@ impl PayloadType {
             | Self::ME
             | Self::MF
             | Self::MG
+            | Self::MH
             | Self::MU
             | Self::MV
             | Self::MW
@@ -311,4 +313,5 @@ impl Display for PayloadType {
 fn test_adjust_with_num() {
     assert_eq!(PayloadType::MA.adjust_with_num(2), "-AAC");
     assert_eq!(PayloadType::MA.adjust_with_num(27), "-AAb");
+    assert_eq!(PayloadType::MH.adjust_with_num(1), "-HAB");
 }
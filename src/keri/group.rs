@@ -0,0 +1,195 @@
+use serde::{Deserialize, Serialize};
+
+use super::Keri;
+use crate::{
+    error::Error,
+    event::{event_data::EventData, sections::threshold::SignatureThreshold, EventMessage},
+    event_message::{
+        event_msg_builder::EventMsgBuilder, key_event_message::KeyEvent,
+        signed_event_message::SignedEventMessage, EventTypeTag,
+    },
+    prefix::{AttachedSignaturePrefix, BasicPrefix, IdentifierPrefix},
+    signer::KeyManager,
+};
+
+/// Group Member
+///
+/// Current and next keys contributed by a single participant to events of
+/// group identifier. Keys of members are listed in group establishment
+/// events in the order of members.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GroupMember {
+    pub keys: Vec<BasicPrefix>,
+    pub next_keys: Vec<BasicPrefix>,
+}
+
+impl<K: KeyManager> Keri<K> {
+    /// Keys of own `KeyManager` to be contributed to group establishment
+    /// event. Before group rotation every member rotates its keys, so its
+    /// previous next keys become current.
+    ///
+    pub fn group_member(&self) -> Result<GroupMember, Error> {
+        let km = self.key_manager.lock().map_err(|_| Error::MutexPoisoned)?;
+        Ok(GroupMember {
+            keys: km.public_keys(self.config.key_derivation)?,
+            next_keys: km.next_public_keys(self.config.key_derivation)?,
+        })
+    }
+
+    /// Makes unsigned inception event of group identifier, which should be
+    /// passed to other members to be signed with `sign_group_event`.
+    ///
+    pub fn make_group_icp(
+        &self,
+        members: &[GroupMember],
+        threshold: SignatureThreshold,
+        next_threshold: SignatureThreshold,
+        initial_witness: Option<Vec<BasicPrefix>>,
+    ) -> Result<EventMessage<KeyEvent>, Error> {
+        let (keys, next_keys) = group_keys(members)?;
+        EventMsgBuilder::new(EventTypeTag::Icp)
+            .with_keys(keys)
+            .with_next_keys(next_keys)
+            .with_threshold(&threshold)
+            .with_next_threshold(&next_threshold)
+            .with_witness_list(&initial_witness.unwrap_or_default())
+            .with_format(self.config.format)
+            .with_derivation(self.config.digest.clone())
+            .build()
+    }
+
+    /// Makes unsigned rotation event of group identifier known from own KEL.
+    ///
+    pub fn make_group_rot(
+        &self,
+        group: &IdentifierPrefix,
+        members: &[GroupMember],
+        threshold: SignatureThreshold,
+        next_threshold: SignatureThreshold,
+    ) -> Result<EventMessage<KeyEvent>, Error> {
+        let state = self
+            .processor
+            .compute_state(group)?
+            .ok_or_else(|| Error::SemanticError("There is no state".into()))?;
+        let (keys, next_keys) = group_keys(members)?;
+        EventMsgBuilder::new(EventTypeTag::Rot)
+            .with_prefix(group)
+            .with_sn(state.sn + 1)
+            .with_previous_event(&state.last_event_digest)
+            .with_keys(keys)
+            .with_next_keys(next_keys)
+            .with_threshold(&threshold)
+            .with_next_threshold(&next_threshold)
+            .with_format(self.config.format)
+            .with_derivation(self.config.digest.clone())
+            .build()
+    }
+
+    /// Makes unsigned interaction event of group identifier known from own
+    /// KEL.
+    ///
+    pub fn make_group_ixn(
+        &self,
+        group: &IdentifierPrefix,
+        payload: Option<&str>,
+    ) -> Result<EventMessage<KeyEvent>, Error> {
+        self.make_ixn_event(group, self.payload_seals(payload))
+    }
+
+    /// Signs group event with own keys. Returned event carries only own
+    /// signatures, indexed by position of own keys in group key list, and
+    /// should be passed to the member assembling the event.
+    ///
+    pub fn sign_group_event(
+        &self,
+        event: &EventMessage<KeyEvent>,
+    ) -> Result<SignedEventMessage, Error> {
+        let group_keys = self.group_event_keys(event)?;
+        let km = self.key_manager.lock().map_err(|_| Error::MutexPoisoned)?;
+        let own_keys = km.public_keys(self.config.key_derivation)?;
+        let signatures = km
+            .sign_indexed(&event.serialize()?, self.config.signature)?
+            .into_iter()
+            .map(|sig| {
+                let key = own_keys
+                    .get(sig.index as usize)
+                    .ok_or_else(|| Error::SemanticError("Key index not present in set".into()))?;
+                let index = group_keys
+                    .iter()
+                    .position(|group_key| group_key == key)
+                    .ok_or_else(|| Error::SemanticError("Not a member of the group".into()))?;
+                Ok(AttachedSignaturePrefix {
+                    index: index as u16,
+                    ..sig
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(SignedEventMessage::new(event, signatures, None))
+    }
+
+    /// Merges signatures of the same group event collected from members and
+    /// processes the assembled event. Signatures not made by group key at
+    /// their index are skipped. Fails if collected signatures don't satisfy
+    /// group threshold.
+    ///
+    pub fn finalize_group_event(
+        &self,
+        partials: &[SignedEventMessage],
+    ) -> Result<SignedEventMessage, Error> {
+        let event = &partials
+            .first()
+            .ok_or_else(|| Error::SemanticError("No signed group event".into()))?
+            .event_message;
+        let group_keys = self.group_event_keys(event)?;
+        let serialized = event.serialize()?;
+        let mut signatures: Vec<AttachedSignaturePrefix> = vec![];
+        for partial in partials {
+            if &partial.event_message != event {
+                return Err(Error::SemanticError("Signed group events differ".into()));
+            }
+            for sig in &partial.signatures {
+                let valid = match group_keys.get(sig.index as usize) {
+                    Some(key) => key.verify(&serialized, &sig.signature)?,
+                    None => false,
+                };
+                if valid && !signatures.iter().any(|s| s.index == sig.index) {
+                    signatures.push(sig.clone());
+                }
+            }
+        }
+        signatures.sort_by_key(|sig| sig.index);
+        let signed = SignedEventMessage::new(event, signatures, None);
+        self.processor.process_own_event(&signed)?;
+        Ok(signed)
+    }
+
+    /// Keys of the group signing `event`: ones it establishes, or current
+    /// group keys for interaction events.
+    fn group_event_keys(&self, event: &EventMessage<KeyEvent>) -> Result<Vec<BasicPrefix>, Error> {
+        Ok(match event.event.get_event_data() {
+            EventData::Icp(icp) => icp.key_config.public_keys,
+            EventData::Rot(rot) => rot.key_config.public_keys,
+            _ => {
+                self.processor
+                    .compute_state(&event.event.get_prefix())?
+                    .ok_or_else(|| Error::SemanticError("Unknown group identifier".into()))?
+                    .current
+                    .public_keys
+            }
+        })
+    }
+}
+
+fn group_keys(members: &[GroupMember]) -> Result<(Vec<BasicPrefix>, Vec<BasicPrefix>), Error> {
+    if members.is_empty() {
+        return Err(Error::SemanticError("Empty group".into()));
+    }
+    Ok(members
+        .iter()
+        .map(|member| (member.keys.clone(), member.next_keys.clone()))
+        .fold((vec![], vec![]), |(mut keys, mut next), (k, n)| {
+            keys.extend(k);
+            next.extend(n);
+            (keys, next)
+        }))
+}
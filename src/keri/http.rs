@@ -0,0 +1,315 @@
+use std::{convert::TryFrom, net::SocketAddr, str::FromStr, sync::Arc};
+
+use async_std::{
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    task,
+};
+use http_types::{Method, Mime, Request, Response, StatusCode, Url};
+
+use super::witness::Witness;
+use crate::{
+    error::Error,
+    event_message::signed_event_message::Message,
+    event_parsing::{message::signed_event_stream, SignedEventData},
+    oobi::OobiTransport,
+    prefix::{IdentifierPrefix, Prefix},
+    query::reply::SignedReply,
+    transport::{self, transport_error},
+};
+
+/// Header carrying attachments of the message sent in request body.
+pub const CESR_ATTACHMENT: &str = "CESR-ATTACHMENT";
+pub const CESR_CONTENT_TYPE: &str = "application/cesr";
+
+fn parse_messages(stream: &[u8]) -> Result<Vec<Message>, Error> {
+    let (rest, messages) =
+        signed_event_stream(stream).map_err(|e| Error::DeserializeError(e.to_string()))?;
+    if !rest.is_empty() {
+        return Err(Error::DeserializeError(
+            "Unparsed data in CESR stream".into(),
+        ));
+    }
+    messages.into_iter().map(Message::try_from).collect()
+}
+
+fn cesr_response(status: StatusCode, body: Vec<u8>) -> Response {
+    let mut response = Response::new(status);
+    response.set_body(body);
+    response.set_content_type(Mime::from_str(CESR_CONTENT_TYPE).unwrap());
+    response
+}
+
+fn error_response(status: StatusCode, e: impl ToString) -> Response {
+    let mut response = Response::new(status);
+    response.set_body(e.to_string());
+    response
+}
+
+/// HTTP Witness Server
+///
+/// Serves `Witness` over HTTP following keripy conventions. CESR streams
+/// are POSTed (or PUT) to `/`, with attachments of single message optionally
+/// carried in `CESR-ATTACHMENT` header, and answered with receipts and
+/// replies. KEL with receipts and KSN of identifier are served at
+/// `GET /query?typ=kel&pre=<prefix>` and `GET /query?typ=ksn&pre=<prefix>`.
+pub struct HttpWitnessServer {
+    listener: TcpListener,
+    witness: Arc<Witness>,
+}
+
+impl HttpWitnessServer {
+    pub async fn bind<A: ToSocketAddrs>(addr: A, witness: Arc<Witness>) -> Result<Self, Error> {
+        let listener = TcpListener::bind(addr).await.map_err(transport_error)?;
+        Ok(Self { listener, witness })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        self.listener.local_addr().map_err(transport_error)
+    }
+
+    /// Answers requests of accepted connections until the listener fails.
+    pub async fn run(self) -> Result<(), Error> {
+        let witness = self.witness;
+        transport::accept(self.listener.incoming(), |stream| {
+            let witness = Arc::clone(&witness);
+            async move {
+                async_h1::accept(stream, |request| {
+                    let witness = Arc::clone(&witness);
+                    async move { Ok(Self::handle(request, &witness).await) }
+                })
+                .await
+                .map_err(transport_error)
+            }
+        })
+        .await
+        .map_err(transport_error)
+    }
+
+    async fn handle(mut request: Request, witness: &Witness) -> Response {
+        match (request.method(), request.url().path()) {
+            (Method::Post, "/") | (Method::Put, "/") => {
+                let attachments = request
+                    .header(CESR_ATTACHMENT)
+                    .map(|values| values.last().as_str().to_string());
+                let mut stream = match request.body_bytes().await {
+                    Ok(body) => body,
+                    Err(e) => return error_response(StatusCode::BadRequest, e),
+                };
+                stream.extend(attachments.unwrap_or_default().as_bytes());
+                let messages = match parse_messages(&stream) {
+                    Ok(messages) => messages,
+                    Err(e) => return error_response(StatusCode::BadRequest, e),
+                };
+                let mut responses = vec![];
+                for message in messages {
+                    match witness.respond(message) {
+                        Ok(response) => responses.extend(response),
+                        Err(e) => return error_response(StatusCode::BadRequest, e),
+                    }
+                }
+                cesr_response(StatusCode::Ok, responses)
+            }
+            (Method::Get, "/query") => {
+                let (mut typ, mut pre) = (None, None);
+                for (key, value) in request.url().query_pairs() {
+                    match key.as_ref() {
+                        "typ" => typ = Some(value.to_string()),
+                        "pre" => pre = Some(value.to_string()),
+                        _ => (),
+                    }
+                }
+                let prefix = match pre.map(|pre| pre.parse::<IdentifierPrefix>()) {
+                    Some(Ok(prefix)) => prefix,
+                    _ => return error_response(StatusCode::BadRequest, "Missing or improper pre"),
+                };
+                let result = match typ.as_deref() {
+                    Some("kel") => witness.processor.get_kerl_with_receipts(&prefix),
+                    Some("ksn") => match witness.processor.compute_state(&prefix) {
+                        Ok(Some(_)) => witness
+                            .get_ksn_for_prefix(&prefix)
+                            .and_then(|rpy| SignedEventData::from(rpy).to_cesr())
+                            .map(Some),
+                        other => other.map(|_| None),
+                    },
+                    _ => return error_response(StatusCode::BadRequest, "Unknown query type"),
+                };
+                match result {
+                    Ok(Some(body)) => cesr_response(StatusCode::Ok, body),
+                    Ok(None) => error_response(StatusCode::NotFound, "Unknown identifier"),
+                    Err(e) => error_response(StatusCode::InternalServerError, e),
+                }
+            }
+            _ => error_response(StatusCode::NotFound, "Not found"),
+        }
+    }
+}
+
+/// HTTP Client
+///
+/// Client of keripy compatible HTTP witness, like `HttpWitnessServer`.
+pub struct HttpClient {
+    url: Url,
+}
+
+impl HttpClient {
+    /// Creates client of witness served at `url`. Witness endpoints are
+    /// resolved relative to its path, so witness may be served under a
+    /// prefix, like `http://example.com/witness/`.
+    pub fn new(url: &str) -> Result<Self, Error> {
+        let mut url = Url::parse(url).map_err(transport_error)?;
+        if url.scheme() != "http" {
+            return Err(Error::TransportError(format!(
+                "Unsupported scheme: {}",
+                url.scheme()
+            )));
+        }
+        if !url.path().ends_with('/') {
+            let path = format!("{}/", url.path());
+            url.set_path(&path);
+        }
+        Ok(Self { url })
+    }
+
+    /// Sends message with attachments in `CESR-ATTACHMENT` header and
+    /// returns messages sent back, like receipts.
+    pub async fn send(&self, message: &SignedEventData) -> Result<Vec<Message>, Error> {
+        let mut request = Request::new(Method::Post, self.url.clone());
+        request.set_body(message.deserialized_event.serialize()?);
+        request.set_content_type(Mime::from_str(CESR_CONTENT_TYPE).unwrap());
+        let attachments: String = message
+            .attachments
+            .iter()
+            .map(|attachment| attachment.to_cesr())
+            .collect();
+        request.insert_header(CESR_ATTACHMENT, attachments);
+        parse_messages(&self.request(request).await?)
+    }
+
+    /// Gets KEL of `prefix` with witness receipts.
+    pub async fn get_kel(&self, prefix: &IdentifierPrefix) -> Result<Vec<Message>, Error> {
+        parse_messages(&self.query("kel", prefix).await?)
+    }
+
+    /// Gets key state notice of `prefix` signed by witness.
+    pub async fn get_ksn(&self, prefix: &IdentifierPrefix) -> Result<SignedReply, Error> {
+        match parse_messages(&self.query("ksn", prefix).await?)?.pop() {
+            Some(Message::KeyStateNotice(rpy)) => Ok(rpy),
+            _ => Err(Error::SemanticError("Expected key state notice".into())),
+        }
+    }
+
+    async fn query(&self, typ: &str, prefix: &IdentifierPrefix) -> Result<Vec<u8>, Error> {
+        let mut url = self.url.join("query").map_err(transport_error)?;
+        url.query_pairs_mut()
+            .append_pair("typ", typ)
+            .append_pair("pre", &prefix.to_str());
+        self.request(Request::new(Method::Get, url)).await
+    }
+
+    async fn request(&self, request: Request) -> Result<Vec<u8>, Error> {
+        send_request(request).await
+    }
+}
+
+async fn send_request(request: Request) -> Result<Vec<u8>, Error> {
+    let addr = request
+        .url()
+        .socket_addrs(|| None)
+        .map_err(transport_error)?
+        .pop()
+        .ok_or_else(|| Error::TransportError("Unresolved address".into()))?;
+    let stream = TcpStream::connect(addr).await.map_err(transport_error)?;
+    let mut response = async_h1::connect(stream, request)
+        .await
+        .map_err(transport_error)?;
+    let body = response.body_bytes().await.map_err(transport_error)?;
+    if response.status().is_success() {
+        Ok(body)
+    } else {
+        Err(Error::TransportError(format!(
+            "{}: {}",
+            response.status(),
+            String::from_utf8_lossy(&body)
+        )))
+    }
+}
+
+/// Fetches OOBI resources with HTTP GET requests.
+pub struct HttpTransport;
+
+impl OobiTransport for HttpTransport {
+    fn get(&self, url: &Url) -> Result<Vec<u8>, Error> {
+        task::block_on(send_request(Request::new(Method::Get, url.clone())))
+    }
+}
+
+#[test]
+fn test_http_witness() -> Result<(), Error> {
+    use crate::{database::sled::SledEventDatabase, keri::Keri, signer::CryptoBox};
+    use std::{sync::Mutex, time::Duration};
+    use tempfile::Builder;
+
+    let witness_root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let witness = Arc::new(Witness::new(witness_root.path())?);
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db = Arc::new(SledEventDatabase::new(root.path())?);
+    let key_manager = Arc::new(Mutex::new(CryptoBox::new()?));
+    let mut alice = Keri::new(db, Arc::clone(&key_manager))?;
+
+    let test = async {
+        let server = HttpWitnessServer::bind("127.0.0.1:0", Arc::clone(&witness)).await?;
+        let client = HttpClient::new(&format!("http://{}", server.local_addr()?))?;
+        task::spawn(async move { server.run().await.ok() });
+
+        // attachments sent in header
+        let icp = alice.incept(Some(vec![witness.prefix.clone()]))?;
+        let receipts = client.send(&SignedEventData::from(&icp)).await?;
+        assert_eq!(receipts.len(), 1);
+        for rct in receipts {
+            alice.processor.process(rct)?;
+        }
+
+        // attachments sent inline
+        let ixn = alice.make_ixn(Some("data"))?;
+        let mut request = Request::new(Method::Post, client.url.clone());
+        request.set_body(SignedEventData::from(&ixn).to_cesr()?);
+        let receipts = parse_messages(&client.request(request).await?)?;
+        assert!(matches!(receipts[..], [Message::NontransferableRct(_)]));
+        assert_eq!(witness.get_receipts(alice.prefix()).len(), 2);
+
+        // kel and ksn are served
+        let kel = client.get_kel(alice.prefix()).await?;
+        assert_eq!(kel.len(), 4);
+        assert!(matches!(kel[0], Message::Event(_)));
+        let ksn = client.get_ksn(alice.prefix()).await?;
+        assert_eq!(ksn.reply.event.get_state()?, alice.get_state()?.unwrap());
+
+        // unknown identifier
+        let bob = "DoQy7bwiYr80qXoISsMdGvfXmCFpQ2ULO8grr2JaW-gU".parse()?;
+        assert!(matches!(
+            client.get_kel(&bob).await,
+            Err(Error::TransportError(_))
+        ));
+        Ok(())
+    };
+    // test shouldn't hang on missing response
+    task::block_on(async_std::future::timeout(Duration::from_secs(10), test)).expect("no response")
+}
+
+#[test]
+fn test_http_client_url() -> Result<(), Error> {
+    // endpoints are resolved under base path
+    let client = HttpClient::new("http://localhost:5631/witness")?;
+    assert_eq!(client.url.as_str(), "http://localhost:5631/witness/");
+    assert_eq!(
+        client.url.join("query").unwrap().as_str(),
+        "http://localhost:5631/witness/query"
+    );
+    let client = HttpClient::new("http://localhost:5631")?;
+    assert_eq!(client.url.as_str(), "http://localhost:5631/");
+
+    // only plain HTTP is spoken
+    assert!(HttpClient::new("https://localhost:5631").is_err());
+    assert!(HttpClient::new("tcp://localhost:5631").is_err());
+    Ok(())
+}
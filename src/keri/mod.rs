@@ -1,17 +1,28 @@
+#[cfg(feature = "query")]
+use std::collections::BTreeMap;
 use std::{
     convert::TryFrom,
     sync::{Arc, Mutex},
 };
 
+#[cfg(feature = "async")]
+use crate::signer::remote::AsyncKeyManager;
 use crate::{
     database::sled::SledEventDatabase,
     derivation::basic::Basic,
     derivation::self_addressing::SelfAddressing,
     derivation::self_signing::SelfSigning,
     error::Error,
-    event::sections::seal::{DigestSeal, Seal},
+    event::sections::seal::{DigestSeal, Seal, SourceSeal},
+    event::sections::{configuration::ConfigurationTrait, WitnessConfig},
     event::{event_data::EventData, receipt::Receipt, Event, EventMessage, SerializationFormats},
-    event::{event_data::InteractionEvent, sections::seal::EventSeal},
+    event::{
+        event_data::{InceptionEvent, InteractionEvent},
+        sections::{
+            key_config::nxt_commitment, seal::EventSeal, threshold::SignatureThreshold,
+            InceptionWitnessConfig, KeyConfig,
+        },
+    },
     event_message::event_msg_builder::EventMsgBuilder,
     event_message::{
         key_event_message::KeyEvent,
@@ -31,17 +42,64 @@ use crate::{
     signer::KeyManager,
     state::{EventSemantics, IdentifierState},
 };
+#[cfg(feature = "query")]
+use crate::{
+    oobi::{EndRole, Role},
+    query::{
+        exchange::{ExchangeEvent, SignedExchange},
+        key_state_notice::KeyStateNotice,
+        mailbox::DELEGATE_TOPIC,
+        query::{QueryArgs, QueryEvent, SignedQuery},
+        reply::{ReplyEvent, ReplyPayload, SignedReply},
+        ReplyType, Route,
+    },
+};
+#[cfg(feature = "query")]
+use serde::{de::DeserializeOwned, Serialize};
 #[cfg(feature = "wallet")]
 use universal_wallet::prelude::{Content, UnlockedWallet};
 
+pub mod group;
+#[cfg(feature = "http")]
+pub mod http;
+#[cfg(all(feature = "async", feature = "query"))]
+pub mod server;
 #[cfg(test)]
 mod test;
 #[cfg(feature = "query")]
+pub mod watcher;
+#[cfg(feature = "query")]
 pub mod witness;
-pub struct Keri<K: KeyManager + 'static> {
+
+/// Keri Config
+///
+/// Derivations and serialization format used for events and receipts
+/// produced by `Keri`. Defaults to Ed25519 keys and signatures, Blake3-256
+/// digests and JSON serialization.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeriConfig {
+    pub key_derivation: Basic,
+    pub signature: SelfSigning,
+    pub digest: SelfAddressing,
+    pub format: SerializationFormats,
+}
+
+impl Default for KeriConfig {
+    fn default() -> Self {
+        Self {
+            key_derivation: Basic::Ed25519,
+            signature: SelfSigning::Ed25519Sha512,
+            digest: SelfAddressing::Blake3_256,
+            format: SerializationFormats::JSON,
+        }
+    }
+}
+
+pub struct Keri<K: 'static> {
     prefix: IdentifierPrefix,
     key_manager: Arc<Mutex<K>>,
     processor: EventProcessor,
+    config: KeriConfig,
 }
 
 #[cfg(feature = "wallet")]
@@ -75,20 +133,52 @@ impl Keri<UnlockedWallet> {
             prefix,
             key_manager: Arc::new(Mutex::new(wallet)),
             processor: EventProcessor::new(db),
+            config: KeriConfig::default(),
         })
     }
 }
 
-impl<K: KeyManager> Keri<K> {
+impl<K> Keri<K> {
     // incept a state and keys
     pub fn new(db: Arc<SledEventDatabase>, key_manager: Arc<Mutex<K>>) -> Result<Keri<K>, Error> {
         Ok(Keri {
             prefix: IdentifierPrefix::default(),
             key_manager,
             processor: EventProcessor::new(db),
+            config: KeriConfig::default(),
         })
     }
 
+    /// Sets derivations and format of produced events. Key manager must
+    /// support configured key derivation and signature derivation must be
+    /// the one of configured keys, as signatures are labeled with it.
+    ///
+    pub fn with_config(self, config: KeriConfig) -> Result<Self, Error>
+    where
+        K: KeyManager,
+    {
+        if config.key_derivation.self_signing() != Some(config.signature) {
+            return Err(Error::SemanticError(
+                "Signature derivation doesn't match key derivation".into(),
+            ));
+        }
+        if !self
+            .key_manager
+            .lock()
+            .map_err(|_| Error::MutexPoisoned)?
+            .supports(config.key_derivation)
+        {
+            return Err(Error::SemanticError(
+                "Key manager doesn't support key derivation".into(),
+            ));
+        }
+        Ok(Keri { config, ..self })
+    }
+
+    pub fn config(&self) -> &KeriConfig {
+        &self.config
+    }
+
     /// Getter of the instance prefix
     ///
     pub fn prefix(&self) -> &IdentifierPrefix {
@@ -107,6 +197,82 @@ impl<K: KeyManager> Keri<K> {
         Arc::clone(&self.processor.db)
     }
 
+    pub fn get_state(&self) -> Result<Option<IdentifierState>, Error> {
+        self.processor.compute_state(&self.prefix)
+    }
+
+    pub fn get_kerl(&self) -> Result<Option<Vec<u8>>, Error> {
+        self.processor.get_kerl(&self.prefix)
+    }
+
+    pub fn get_state_for_prefix(
+        &self,
+        prefix: &IdentifierPrefix,
+    ) -> Result<Option<IdentifierState>, Error> {
+        self.processor.compute_state(prefix)
+    }
+
+    pub fn get_state_for_seal(&self, seal: &EventSeal) -> Result<Option<IdentifierState>, Error> {
+        self.processor.compute_state_at_sn(&seal.prefix, seal.sn)
+    }
+
+    /// Registers handler of replies with application `route`, run with
+    /// deserialized reply data once the reply is accepted.
+    ///
+    #[cfg(feature = "query")]
+    pub fn register_reply_handler<P, F>(&self, route: &str, handler: F) -> Result<(), Error>
+    where
+        P: DeserializeOwned,
+        F: Fn(&SignedReply, P) -> Result<(), Error> + Send + Sync + 'static,
+    {
+        self.processor.register_reply_handler(route, handler)
+    }
+
+    /// Registers handler of `exn` messages with `route`, run with
+    /// deserialized message payload once its signatures are verified.
+    ///
+    #[cfg(feature = "query")]
+    pub fn register_exchange_handler<P, F>(&self, route: &str, handler: F) -> Result<(), Error>
+    where
+        P: DeserializeOwned,
+        F: Fn(&SignedExchange, P) -> Result<(), Error> + Send + Sync + 'static,
+    {
+        self.processor.register_exchange_handler(route, handler)
+    }
+
+    fn payload_seals(&self, payload: Option<&str>) -> Vec<Seal> {
+        match payload {
+            Some(payload) => {
+                vec![Seal::Digest(DigestSeal {
+                    dig: self.config.digest.derive(payload.as_bytes()),
+                })]
+            }
+            None => vec![],
+        }
+    }
+
+    fn make_ixn_event(
+        &self,
+        prefix: &IdentifierPrefix,
+        seal_list: Vec<Seal>,
+    ) -> Result<EventMessage<KeyEvent>, Error> {
+        let state = self
+            .processor
+            .compute_state(prefix)?
+            .ok_or_else(|| Error::SemanticError("There is no state".into()))?;
+
+        EventMsgBuilder::new(EventTypeTag::Ixn)
+            .with_prefix(prefix)
+            .with_sn(state.sn + 1)
+            .with_previous_event(&state.last_event_digest)
+            .with_seal(seal_list)
+            .with_format(self.config.format)
+            .with_derivation(self.config.digest.clone())
+            .build()
+    }
+}
+
+impl<K: KeyManager> Keri<K> {
     pub fn process(&self, id: &IdentifierPrefix, event: impl EventSemantics) -> Result<(), Error> {
         match self.processor.process_actual_event(id, event) {
             Ok(Some(_)) => Ok(()),
@@ -118,26 +284,37 @@ impl<K: KeyManager> Keri<K> {
     pub fn incept(
         &mut self,
         initial_witness: Option<Vec<BasicPrefix>>,
+    ) -> Result<SignedEventMessage, Error> {
+        self.incept_with_configuration(initial_witness, &[])
+    }
+
+    /// Incepts identifier with given configuration traits, eg.
+    /// establishment only identifier which can't make `ixn` events.
+    ///
+    pub fn incept_with_configuration(
+        &mut self,
+        initial_witness: Option<Vec<BasicPrefix>>,
+        configuration: &[ConfigurationTrait],
     ) -> Result<SignedEventMessage, Error> {
         let km = self.key_manager.lock().map_err(|_| Error::MutexPoisoned)?;
         let icp = EventMsgBuilder::new(EventTypeTag::Icp)
             .with_prefix(&self.prefix)
-            .with_keys(vec![Basic::Ed25519.derive(km.public_key()?)])
-            .with_next_keys(vec![Basic::Ed25519.derive(km.next_public_key()?)])
+            .with_keys(km.public_keys(self.config.key_derivation)?)
+            .with_next_keys(km.next_public_keys(self.config.key_derivation)?)
+            .with_threshold(&km.threshold())
+            .with_next_threshold(&km.next_threshold())
             .with_witness_list(&initial_witness.unwrap_or_default())
+            .with_configuration(configuration)
+            .with_format(self.config.format)
+            .with_derivation(self.config.digest.clone())
             .build()?;
 
         let signed = icp.sign(
-            vec![AttachedSignaturePrefix::new(
-                SelfSigning::Ed25519Sha512,
-                km.sign(&icp.serialize()?)?,
-                0,
-            )],
+            km.sign_indexed(&icp.serialize()?, self.config.signature)?,
             None,
         );
 
-        self.processor
-            .process(Message::Event(Box::new(signed.clone())))?;
+        self.processor.process_own_event(&signed)?;
 
         self.prefix = icp.event.get_prefix();
 
@@ -164,23 +341,110 @@ impl<K: KeyManager> Keri<K> {
             .collect();
         // Signing key must be first
         let km = self.key_manager.lock().map_err(|_| Error::MutexPoisoned)?;
-        keys.insert(0, Basic::Ed25519.derive(km.public_key()?));
+        keys.insert(0, self.config.key_derivation.derive(km.public_key()?));
         let icp = EventMsgBuilder::new(EventTypeTag::Icp)
             .with_prefix(&self.prefix)
             .with_keys(keys)
-            .with_next_keys(vec![Basic::Ed25519.derive(km.next_public_key()?)])
+            .with_next_keys(vec![self
+                .config
+                .key_derivation
+                .derive(km.next_public_key()?)])
+            .with_format(self.config.format)
+            .with_derivation(self.config.digest.clone())
             .build()?;
 
         let signed = icp.sign(
             vec![AttachedSignaturePrefix::new(
-                SelfSigning::Ed25519Sha512,
+                self.config.signature,
                 km.sign(&icp.serialize()?)?,
                 0,
             )],
             None,
         );
-        self.processor
-            .process(Message::Event(Box::new(signed.clone())))?;
+        self.processor.process_own_event(&signed)?;
+        self.prefix = icp.event.get_prefix();
+
+        Ok(signed)
+    }
+
+    /// Incepts ephemeral non-transferable identifier, which prefix is the
+    /// current public key. It commits to no next keys, so it can't be
+    /// rotated and no event can follow inception.
+    ///
+    pub fn incept_nontransferable(&mut self) -> Result<SignedEventMessage, Error> {
+        let derivation = match self.config.key_derivation {
+            Basic::Ed25519 | Basic::Ed25519NT => Basic::Ed25519NT,
+            Basic::ECDSAsecp256k1 | Basic::ECDSAsecp256k1NT => Basic::ECDSAsecp256k1NT,
+            Basic::Ed448 | Basic::Ed448NT => Basic::Ed448NT,
+            _ => return Err(Error::ImproperPrefixType),
+        };
+        let km = self.key_manager.lock().map_err(|_| Error::MutexPoisoned)?;
+        let icp = EventMsgBuilder::new(EventTypeTag::Icp)
+            .with_keys(vec![derivation.derive(km.public_key()?)])
+            .with_next_keys(vec![])
+            .with_format(self.config.format)
+            .with_derivation(self.config.digest.clone())
+            .build()?;
+
+        let signed = icp.sign(
+            vec![AttachedSignaturePrefix::new(
+                self.config.signature,
+                km.sign(&icp.serialize()?)?,
+                0,
+            )],
+            None,
+        );
+        self.processor.process_own_event(&signed)?;
+        self.prefix = icp.event.get_prefix();
+
+        Ok(signed)
+    }
+
+    /// Incepts self-signing identifier, which prefix is signature of the
+    /// current key over inception data. Unlike basic prefix, it commits to
+    /// the whole inception event and may be rotated as usual.
+    ///
+    pub fn incept_self_signing(
+        &mut self,
+        initial_witness: Option<Vec<BasicPrefix>>,
+    ) -> Result<SignedEventMessage, Error> {
+        let km = self.key_manager.lock().map_err(|_| Error::MutexPoisoned)?;
+        let next_keys = vec![self.config.key_derivation.derive(km.next_public_key()?)];
+        let icp_data = InceptionEvent::new(
+            KeyConfig::new(
+                vec![self.config.key_derivation.derive(km.public_key()?)],
+                Some(nxt_commitment(
+                    &SignatureThreshold::default(),
+                    &next_keys,
+                    &self.config.digest,
+                )),
+                Some(SignatureThreshold::default()),
+            ),
+            Some(InceptionWitnessConfig {
+                tally: 0,
+                initial_witnesses: initial_witness.unwrap_or_default(),
+            }),
+            None,
+        );
+        let prefix_signature = SelfSigningPrefix::new(
+            self.config.signature,
+            km.sign(&icp_data.self_signing_data(&self.config.digest, self.config.format)?)?,
+        );
+        let icp = icp_data.incept_self_signing(
+            prefix_signature,
+            self.config.digest.clone(),
+            self.config.format,
+        )?;
+
+        let signed = icp.sign(
+            vec![AttachedSignaturePrefix::new(
+                self.config.signature,
+                km.sign(&icp.serialize()?)?,
+                0,
+            )],
+            None,
+        );
+        self.processor.process_own_event(&signed)?;
         self.prefix = icp.event.get_prefix();
 
         Ok(signed)
@@ -212,19 +476,13 @@ impl<K: KeyManager> Keri<K> {
             next_sn,
             EventData::Ixn(InteractionEvent::new(pref, vec![seal])),
         )
-        .to_message(SerializationFormats::JSON, &SelfAddressing::Blake3_256)?;
-        let serialized = event.serialize()?;
-        let signature = self
+        .to_message(self.config.format, &self.config.digest)?;
+        let signatures = self
             .key_manager
             .lock()
             .map_err(|_| Error::MutexPoisoned)?
-            .sign(&serialized)?;
-        let asp = AttachedSignaturePrefix::new(
-            SelfSigning::ECDSAsecp256k1Sha256,
-            signature,
-            0, // TODO: what is this?
-        );
-        let signed = SignedEventMessage::new(&event, vec![asp], None);
+            .sign_indexed(&event.serialize()?, self.config.signature)?;
+        let signed = SignedEventMessage::new(&event, signatures, None);
         self.processor
             .db
             .add_kel_finalized_event(signed.clone(), &self.prefix)?;
@@ -232,83 +490,452 @@ impl<K: KeyManager> Keri<K> {
     }
 
     pub fn rotate(&mut self) -> Result<SignedEventMessage, Error> {
+        let tally = self
+            .get_state()?
+            .ok_or_else(|| Error::SemanticError("There is no state".into()))?
+            .tally;
+        self.rotate_witnesses(&[], &[], tally)
+    }
+
+    /// Rotates keys together with witness pool. Witness changes are
+    /// validated against current state before keys get rotated.
+    ///
+    pub fn rotate_witnesses(
+        &mut self,
+        add: &[BasicPrefix],
+        remove: &[BasicPrefix],
+        new_tally: u64,
+    ) -> Result<SignedEventMessage, Error> {
+        let state = self
+            .processor
+            .compute_state(&self.prefix)?
+            .ok_or_else(|| Error::SemanticError("There is no state".into()))?;
+        if state.is_abandoned() {
+            return Err(Error::AbandonedIdentifierError);
+        }
+        let witness_config = WitnessConfig {
+            tally: new_tally,
+            prune: remove.to_vec(),
+            graft: add.to_vec(),
+        };
+        if state.config.contains(&ConfigurationTrait::NoBackers) && !add.is_empty() {
+            return Err(Error::SemanticError(
+                "Witnesses of identifier without backers".into(),
+            ));
+        }
+        witness_config.rotate_witnesses(&state.witnesses)?;
+
         self.key_manager
             .lock()
             .map_err(|_| Error::MutexPoisoned)?
             .rotate()?;
-        let rot = self.make_rotation()?;
-        let rot = rot.sign(
-            vec![AttachedSignaturePrefix::new(
-                SelfSigning::Ed25519Sha512,
-                self.key_manager
-                    .lock()
-                    .map_err(|_| Error::MutexPoisoned)?
-                    .sign(&rot.serialize()?)?,
-                0,
-            )],
-            None,
-        );
-
-        self.processor
-            .process(Message::Event(Box::new(rot.clone())))?;
-
-        Ok(rot)
+        let rot = self.make_rotation(&state, witness_config)?.build()?;
+        self.sign_and_process(rot)
     }
 
-    fn make_rotation(&self) -> Result<EventMessage<KeyEvent>, Error> {
+    /// Rotates to next keys without committing to any further keys, so
+    /// no event can follow and the identifier is abandoned for good.
+    ///
+    pub fn abandon(&mut self) -> Result<SignedEventMessage, Error> {
         let state = self
             .processor
             .compute_state(&self.prefix)?
             .ok_or_else(|| Error::SemanticError("There is no state".into()))?;
+        if state.is_abandoned() {
+            return Err(Error::AbandonedIdentifierError);
+        }
+        self.key_manager
+            .lock()
+            .map_err(|_| Error::MutexPoisoned)?
+            .rotate()?;
+        let witness_config = WitnessConfig {
+            tally: state.tally,
+            ..WitnessConfig::default()
+        };
+        let rot = self
+            .make_rotation(&state, witness_config)?
+            .with_next_keys(vec![])
+            .build()?;
+        self.sign_and_process(rot)
+    }
+
+    fn make_rotation(
+        &self,
+        state: &IdentifierState,
+        witness_config: WitnessConfig,
+    ) -> Result<EventMsgBuilder, Error> {
         match self.key_manager.lock() {
-            Ok(kv) => EventMsgBuilder::new(EventTypeTag::Rot)
+            Ok(kv) => Ok(EventMsgBuilder::new(EventTypeTag::Rot)
                 .with_prefix(&self.prefix)
                 .with_sn(state.sn + 1)
                 .with_previous_event(&state.last_event_digest)
-                .with_keys(vec![Basic::Ed25519.derive(kv.public_key()?)])
-                .with_next_keys(vec![Basic::Ed25519.derive(kv.next_public_key()?)])
-                .build(),
+                .with_keys(kv.public_keys(self.config.key_derivation)?)
+                .with_next_keys(kv.next_public_keys(self.config.key_derivation)?)
+                .with_threshold(&kv.threshold())
+                .with_next_threshold(&kv.next_threshold())
+                .with_witness_to_add(&witness_config.graft)
+                .with_witness_to_remove(&witness_config.prune)
+                .with_witness_threshold(witness_config.tally)
+                .with_format(self.config.format)
+                .with_derivation(self.config.digest.clone())),
             Err(_) => Err(Error::MutexPoisoned),
         }
     }
 
+    fn sign_and_process(&self, event: EventMessage<KeyEvent>) -> Result<SignedEventMessage, Error> {
+        let signed = event.sign(
+            self.key_manager
+                .lock()
+                .map_err(|_| Error::MutexPoisoned)?
+                .sign_indexed(&event.serialize()?, self.config.signature)?,
+            None,
+        );
+
+        self.processor.process_own_event(&signed)?;
+
+        Ok(signed)
+    }
+
     pub fn make_ixn(&mut self, payload: Option<&str>) -> Result<SignedEventMessage, Error> {
-        let seal_list = match payload {
-            Some(payload) => {
-                vec![Seal::Digest(DigestSeal {
-                    dig: SelfAddressing::Blake3_256.derive(payload.as_bytes()),
-                })]
-            }
-            None => vec![],
-        };
-        let state = self
-            .processor
-            .compute_state(&self.prefix)?
-            .ok_or_else(|| Error::SemanticError("There is no state".into()))?;
+        self.anchor(self.payload_seals(payload))
+    }
 
-        let ev = EventMsgBuilder::new(EventTypeTag::Ixn)
-            .with_prefix(&self.prefix)
-            .with_sn(state.sn + 1)
-            .with_previous_event(&state.last_event_digest)
-            .with_seal(seal_list)
+    /// Makes interaction event anchoring given seals in own KEL
+    ///
+    pub fn anchor(&self, seals: Vec<Seal>) -> Result<SignedEventMessage, Error> {
+        let ixn = self.make_ixn_event(&self.prefix, seals)?;
+        self.sign_and_process(ixn)
+    }
+
+    /// Makes delegated inception event to be approved by `delegator`.
+    /// Returned event lacks source seal, so it can't be processed until
+    /// delegator anchors it and the seal is attached with
+    /// `finalize_delegation`.
+    ///
+    pub fn incept_delegated(
+        &self,
+        delegator: &IdentifierPrefix,
+    ) -> Result<SignedEventMessage, Error> {
+        let km = self.key_manager.lock().map_err(|_| Error::MutexPoisoned)?;
+        let dip = EventMsgBuilder::new(EventTypeTag::Dip)
+            .with_delegator(delegator)
+            .with_keys(km.public_keys(self.config.key_derivation)?)
+            .with_next_keys(km.next_public_keys(self.config.key_derivation)?)
+            .with_threshold(&km.threshold())
+            .with_next_threshold(&km.next_threshold())
+            .with_format(self.config.format)
+            .with_derivation(self.config.digest.clone())
             .build()?;
 
-        let ixn = ev.sign(
-            vec![AttachedSignaturePrefix::new(
-                SelfSigning::Ed25519Sha512,
-                self.key_manager
-                    .lock()
-                    .map_err(|_| Error::MutexPoisoned)?
-                    .sign(&ev.serialize()?)?,
-                0,
-            )],
+        Ok(dip.sign(
+            km.sign_indexed(&dip.serialize()?, self.config.signature)?,
             None,
+        ))
+    }
+
+    /// Approves delegated event (`dip` or `drt`) of identifier delegated
+    /// to us. Seal of the event is anchored in our KEL and the event is
+    /// processed, so delegate's KEL is known to us. Event designating
+    /// witnesses stays escrowed until they receipt it. Returns source seal
+    /// of the anchoring event, which delegate attaches to its event.
+    ///
+    pub fn approve_delegation(&self, request: &SignedEventMessage) -> Result<SourceSeal, Error> {
+        let event = &request.event_message;
+        match event.event.get_event_data() {
+            EventData::Dip(_) | EventData::Drt(_) => (),
+            _ => return Err(Error::SemanticError("Not a delegated event".into())),
+        };
+        let state = self.processor.apply_to_state(event)?;
+        if state.delegator.as_ref() != Some(&self.prefix) {
+            return Err(Error::SemanticError("Event not delegated to us".into()));
+        }
+        let own_config = self.get_state()?.map(|s| s.config).unwrap_or_default();
+        if own_config.contains(&ConfigurationTrait::DoNotDelegate) {
+            return Err(Error::SemanticError("Delegation not allowed".into()));
+        }
+        if !state
+            .current
+            .verify(&event.serialize()?, &request.signatures)?
+        {
+            return Err(Error::SignatureVerificationError);
+        }
+
+        let ixn = self.anchor(vec![Seal::Event(EventSeal {
+            prefix: event.event.get_prefix(),
+            sn: event.event.get_sn(),
+            event_digest: event.get_digest(),
+        })])?;
+        let source_seal = SourceSeal::new(
+            ixn.event_message.event.get_sn(),
+            ixn.event_message.get_digest(),
         );
+        match self.processor.process(Message::Event(Box::new(
+            event.sign(request.signatures.clone(), Some(source_seal.clone())),
+        ))) {
+            // approved anyway, delegate's witnesses just didn't receipt it yet
+            Ok(_) | Err(Error::NotEnoughReceiptsError) => Ok(source_seal),
+            Err(e) => Err(e),
+        }
+    }
 
-        self.processor
-            .process(Message::Event(Box::new(ixn.clone())))?;
+    /// Attaches delegator's source seal to approved event and processes
+    /// it. Delegator's KEL with anchoring event has to be processed first.
+    ///
+    pub fn finalize_delegation(
+        &mut self,
+        request: &SignedEventMessage,
+        source_seal: SourceSeal,
+    ) -> Result<SignedEventMessage, Error> {
+        let signed = request
+            .event_message
+            .sign(request.signatures.clone(), Some(source_seal));
+        self.processor.process_own_event(&signed)?;
+        if let EventData::Dip(_) = signed.event_message.event.get_event_data() {
+            self.prefix = signed.event_message.event.get_prefix();
+        }
 
-        Ok(ixn)
+        Ok(signed)
+    }
+
+    /// Authorizes endpoint `eid` to play `role` for us. Returned
+    /// `/end/role/add` reply is accepted in own database and should be
+    /// published, e.g. along with our OOBI.
+    ///
+    #[cfg(feature = "query")]
+    pub fn add_end_role(&self, role: Role, eid: &IdentifierPrefix) -> Result<SignedReply, Error> {
+        self.make_end_role(Route::EndRoleAdd, role, eid)
+    }
+
+    /// Revokes authorization of endpoint `eid` to play `role` for us.
+    ///
+    #[cfg(feature = "query")]
+    pub fn cut_end_role(&self, role: Role, eid: &IdentifierPrefix) -> Result<SignedReply, Error> {
+        self.make_end_role(Route::EndRoleCut, role, eid)
+    }
+
+    #[cfg(feature = "query")]
+    fn make_end_role(
+        &self,
+        route: Route,
+        role: Role,
+        eid: &IdentifierPrefix,
+    ) -> Result<SignedReply, Error> {
+        let end_role = EndRole {
+            cid: self.prefix.clone(),
+            role,
+            eid: eid.clone(),
+        };
+        let signed_rpy = self.sign_reply(ReplyPayload::EndRole(end_role), route)?;
+        self.processor.process_signed_reply(&signed_rpy)?;
+        Ok(signed_rpy)
+    }
+
+    /// Makes reply publishing application `data` under `route`, signed by
+    /// us. It's accepted by peers who registered handler of the route.
+    ///
+    #[cfg(feature = "query")]
+    pub fn make_reply<D: Serialize>(&self, route: &str, data: &D) -> Result<SignedReply, Error> {
+        self.sign_reply(
+            ReplyPayload::Data(serde_json::to_value(data)?),
+            Route::custom(route)?,
+        )
+    }
+
+    /// Makes `exn` message carrying `data` to `recipient` under `route`,
+    /// signed with our current keys. Witnesses of the recipient keep it in
+    /// its mailbox.
+    ///
+    #[cfg(feature = "query")]
+    pub fn make_exchange<D: Serialize>(
+        &self,
+        recipient: &IdentifierPrefix,
+        route: &str,
+        data: &D,
+    ) -> Result<SignedExchange, Error> {
+        let exn = ExchangeEvent::new_exchange(
+            Route::custom(route)?,
+            serde_json::json!({ "pre": recipient }),
+            serde_json::to_value(data)?,
+            self.config.digest.clone(),
+            self.config.format,
+        )?;
+        let signatures = self
+            .key_manager
+            .lock()
+            .map_err(|_| Error::MutexPoisoned)?
+            .sign_indexed(&exn.serialize()?, self.config.signature)?;
+        let seal = self
+            .processor
+            .get_last_establishment_event_seal(&self.prefix)?
+            .ok_or_else(|| Error::SemanticError("No establishment event seal".into()))?;
+        Ok(SignedExchange::new(exn, seal, signatures))
+    }
+
+    #[cfg(feature = "query")]
+    fn sign_reply(&self, data: ReplyPayload, route: Route) -> Result<SignedReply, Error> {
+        let rpy =
+            ReplyEvent::new_reply(data, route, self.config.digest.clone(), self.config.format)?;
+        let signatures = self
+            .key_manager
+            .lock()
+            .map_err(|_| Error::MutexPoisoned)?
+            .sign_indexed(&rpy.serialize()?, self.config.signature)?;
+        let seal = self
+            .processor
+            .get_last_establishment_event_seal(&self.prefix)?
+            .ok_or_else(|| Error::SemanticError("No establishment event seal".into()))?;
+        Ok(SignedReply::new_trans(rpy, seal, signatures))
+    }
+
+    /// Makes query for KEL of `prefix` with witness receipts, addressed to
+    /// `witness` and signed by us. Answer should be passed to
+    /// `process_query_response`.
+    ///
+    #[cfg(feature = "query")]
+    pub fn query_kel(
+        &self,
+        witness: &BasicPrefix,
+        prefix: &IdentifierPrefix,
+    ) -> Result<SignedQuery, Error> {
+        self.sign_query(
+            Route::Log,
+            QueryArgs::new(prefix.clone()).with_source(IdentifierPrefix::Basic(witness.clone())),
+        )
+    }
+
+    /// Makes query for key state notice of `prefix`, addressed to
+    /// `witness` and signed by us.
+    ///
+    #[cfg(feature = "query")]
+    pub fn query_ksn(
+        &self,
+        witness: &BasicPrefix,
+        prefix: &IdentifierPrefix,
+    ) -> Result<SignedQuery, Error> {
+        self.sign_query(
+            Route::Ksn,
+            QueryArgs::new(prefix.clone()).with_source(IdentifierPrefix::Basic(witness.clone())),
+        )
+    }
+
+    /// Makes query for our messages waiting in mailbox of `witness`.
+    /// `topics` maps every requested topic to index of its first message
+    /// we haven't got yet, as returned in cursors of previous response.
+    ///
+    #[cfg(feature = "query")]
+    pub fn query_mailbox(
+        &self,
+        witness: &BasicPrefix,
+        topics: BTreeMap<String, u64>,
+    ) -> Result<SignedQuery, Error> {
+        self.sign_query(
+            Route::Mbx,
+            QueryArgs::new(self.prefix.clone())
+                .with_source(IdentifierPrefix::Basic(witness.clone()))
+                .with_topics(topics),
+        )
+    }
+
+    /// Makes query with given `args`, signed by us, e.g. to ask only for
+    /// the tail of KEL we are missing.
+    ///
+    #[cfg(feature = "query")]
+    pub fn sign_query(&self, route: Route, args: QueryArgs) -> Result<SignedQuery, Error> {
+        let qry = QueryEvent::new_query_with_args(
+            route,
+            args,
+            "",
+            self.config.format,
+            &self.config.digest,
+        )?;
+        let signatures = self
+            .key_manager
+            .lock()
+            .map_err(|_| Error::MutexPoisoned)?
+            .sign_indexed(&qry.serialize()?, self.config.signature)?;
+        Ok(SignedQuery::new(qry, self.prefix.clone(), signatures))
+    }
+
+    /// Processes answer to our query. Events and receipts of returned KEL
+    /// are processed into our database, with events we already know
+    /// skipped. Events are escrowed until receipts following them make
+    /// witness agreement. Returned reply, like key state notice, is accepted if it's
+    /// newer than the known one. Messages from mailbox are processed one
+    /// by one, skipping delegation requests waiting for our approval and
+    /// `exn` messages without registered handler, and ignoring events
+    /// which can't be accepted yet. They come as `/mbx` reply, which must
+    /// be signed by one of our witnesses.
+    ///
+    #[cfg(feature = "query")]
+    pub fn process_query_response(&self, response: ReplyType) -> Result<(), Error> {
+        match response {
+            ReplyType::Kel(kel) => {
+                let messages = signed_event_stream(&kel)
+                    .map_err(|e| Error::DeserializeError(e.to_string()))?
+                    .1;
+                for message in messages {
+                    match self.processor.process(Message::try_from(message)?) {
+                        Ok(_)
+                        | Err(Error::EventDuplicateError)
+                        | Err(Error::NotEnoughReceiptsError) => (),
+                        Err(e) => return Err(e),
+                    }
+                }
+                Ok(())
+            }
+            ReplyType::Rep(rpy) if rpy.reply.event.get_route() == Route::ReplyMbx => {
+                let witnesses = self.get_state()?.map(|state| state.witnesses);
+                match rpy.signature.get_signer() {
+                    IdentifierPrefix::Basic(signer)
+                        if witnesses.is_some_and(|witnesses| witnesses.contains(&signer)) => {}
+                    _ => {
+                        return Err(Error::SemanticError(
+                            "Mailbox reply not signed by our witness".into(),
+                        ))
+                    }
+                };
+                self.processor
+                    .verify(&rpy.reply.serialize()?, &rpy.signature)?;
+                rpy.reply.check_digest()?;
+                match rpy.reply.event.get_reply_data() {
+                    ReplyPayload::Mbx(mbx) => self.process_query_response(ReplyType::Mbx(mbx)),
+                    _ => Err(Error::SemanticError("Improper mailbox reply".into())),
+                }
+            }
+            ReplyType::Rep(rpy) => self.processor.process_signed_reply(&rpy).map(|_| ()),
+            ReplyType::Mbx(mbx) => {
+                for (topic, messages) in &mbx.topics {
+                    // they wait for our approval, see `approve_delegation`
+                    if topic == DELEGATE_TOPIC {
+                        continue;
+                    }
+                    let messages = signed_event_stream(&messages.concat())
+                        .map_err(|e| Error::DeserializeError(e.to_string()))?
+                        .1;
+                    for message in messages {
+                        let message = match Message::try_from(message)? {
+                            // left to the application, if it has no handler
+                            Message::Exchange(exn)
+                                if !self
+                                    .processor
+                                    .has_exchange_handler(&exn.exchange.event.get_route())? =>
+                            {
+                                continue
+                            }
+                            message => message,
+                        };
+                        match self.processor.process(message) {
+                            Ok(_)
+                            | Err(Error::EventDuplicateError)
+                            | Err(Error::NotEnoughReceiptsError)
+                            | Err(Error::NotEnoughSigsError) => (),
+                            Err(e) => return Err(e),
+                        }
+                    }
+                }
+                Ok(())
+            }
+        }
     }
 
     /// Process and respond to single event
@@ -361,9 +988,11 @@ impl<K: KeyManager> Keri<K> {
                         buf.append(&mut rcp.to_cesr().unwrap());
                         Ok(buf)
                     }
-                    Message::TransferableRct(_rct) => Ok(vec![]),
-                    // TODO: this should process properly
-                    _ => todo!(),
+                    Message::NontransferableRct(_) | Message::TransferableRct(_) => Ok(vec![]),
+                    #[cfg(feature = "query")]
+                    Message::Query(qry) => self.answer_query(&qry),
+                    #[cfg(feature = "query")]
+                    Message::KeyStateNotice(_) | Message::Exchange(_) => Ok(vec![]),
                 }
             })
             .filter_map(|x| x.ok())
@@ -372,16 +1001,44 @@ impl<K: KeyManager> Keri<K> {
         Ok(response)
     }
 
+    /// Answers `log` query with KEL and receipts, and `ksn` query with key
+    /// state notice signed by us. Query is expected to be verified already.
+    #[cfg(feature = "query")]
+    fn answer_query(&self, qry: &SignedQuery) -> Result<Vec<u8>, Error> {
+        let args = qry.envelope.event.get_query_data().data;
+        if args.src.as_ref().is_some_and(|src| src != &self.prefix) {
+            return Err(Error::SemanticError(
+                "Query addressed to other identifier".into(),
+            ));
+        }
+        match qry.envelope.event.get_route() {
+            Route::Log => self.processor.get_queried_kel(&args),
+            Route::Ksn => {
+                let state = self
+                    .processor
+                    .compute_state(&args.i)?
+                    .ok_or_else(|| Error::SemanticError("No identifier in db".into()))?;
+                let ksn = KeyStateNotice::new_ksn(state, self.config.format);
+                let rpy = self.sign_reply(
+                    ReplyPayload::Ksn(Box::new(ksn)),
+                    Route::ReplyKsn(self.prefix.clone()),
+                )?;
+                SignedEventData::from(rpy).to_cesr()
+            }
+            _ => Err(Error::SemanticError("Unsupported query route".into())),
+        }
+    }
+
     pub fn make_rct(
         &self,
         event: EventMessage<KeyEvent>,
     ) -> Result<SignedTransferableReceipt, Error> {
         let ser = event.serialize()?;
-        let signature = self
+        let signatures = self
             .key_manager
             .lock()
             .map_err(|_| Error::MutexPoisoned)?
-            .sign(&ser)?;
+            .sign_indexed(&ser, self.config.signature)?;
         let validator_event_seal = self
             .processor
             .get_last_establishment_event_seal(&self.prefix)?
@@ -389,15 +1046,10 @@ impl<K: KeyManager> Keri<K> {
         let rcp = Receipt {
             prefix: event.event.get_prefix(),
             sn: event.event.get_sn(),
-            receipted_event_digest: SelfAddressing::Blake3_256.derive(&ser),
+            receipted_event_digest: self.config.digest.derive(&ser),
         }
-        .to_message(SerializationFormats::JSON)?;
+        .to_message(self.config.format)?;
 
-        let signatures = vec![AttachedSignaturePrefix::new(
-            SelfSigning::Ed25519Sha512,
-            signature,
-            0,
-        )];
         let signed_rcp = SignedTransferableReceipt::new(rcp, validator_event_seal, signatures);
 
         self.processor
@@ -463,25 +1115,6 @@ impl<K: KeyManager> Keri<K> {
         }
     }
 
-    pub fn get_state(&self) -> Result<Option<IdentifierState>, Error> {
-        self.processor.compute_state(&self.prefix)
-    }
-
-    pub fn get_kerl(&self) -> Result<Option<Vec<u8>>, Error> {
-        self.processor.get_kerl(&self.prefix)
-    }
-
-    pub fn get_state_for_prefix(
-        &self,
-        prefix: &IdentifierPrefix,
-    ) -> Result<Option<IdentifierState>, Error> {
-        self.processor.compute_state(prefix)
-    }
-
-    pub fn get_state_for_seal(&self, seal: &EventSeal) -> Result<Option<IdentifierState>, Error> {
-        self.processor.compute_state_at_sn(&seal.prefix, seal.sn)
-    }
-
     fn generate_ntr(
         &self,
         message: EventMessage<KeyEvent>,
@@ -491,17 +1124,17 @@ impl<K: KeyManager> Keri<K> {
         match self.key_manager.lock() {
             Ok(km) => {
                 signature = km.sign(&message.serialize()?)?;
-                bp = BasicPrefix::new(Basic::Ed25519, km.public_key()?);
+                bp = BasicPrefix::new(self.config.key_derivation, km.public_key()?);
             }
             Err(_) => return Err(Error::MutexPoisoned),
         }
-        let ssp = SelfSigningPrefix::new(SelfSigning::Ed25519Sha512, signature);
+        let ssp = SelfSigningPrefix::new(self.config.signature, signature);
         let rcp = Receipt {
             prefix: message.event.get_prefix(),
             sn: message.event.get_sn(),
-            receipted_event_digest: SelfAddressing::Blake3_256.derive(&message.serialize()?),
+            receipted_event_digest: self.config.digest.derive(&message.serialize()?),
         }
-        .to_message(SerializationFormats::JSON)?;
+        .to_message(self.config.format)?;
         let ntr = SignedNontransferableReceipt::new(&rcp, vec![(bp, ssp)]);
         self.processor
             .db
@@ -510,6 +1143,92 @@ impl<K: KeyManager> Keri<K> {
     }
 }
 
+#[cfg(feature = "async")]
+impl<K: AsyncKeyManager> Keri<K> {
+    /// Incepts identifier with keys held by asynchronous signer.
+    /// Signer is not locked while its response is awaited.
+    ///
+    pub async fn incept_async(
+        &mut self,
+        initial_witness: Option<Vec<BasicPrefix>>,
+    ) -> Result<SignedEventMessage, Error> {
+        let keys = self.lock_signer()?.public_keys(self.config.key_derivation);
+        let keys = keys.await?;
+        let icp = EventMsgBuilder::new(EventTypeTag::Icp)
+            .with_prefix(&self.prefix)
+            .with_keys(keys.current)
+            .with_next_keys(keys.next)
+            .with_threshold(&keys.threshold)
+            .with_next_threshold(&keys.next_threshold)
+            .with_witness_list(&initial_witness.unwrap_or_default())
+            .with_format(self.config.format)
+            .with_derivation(self.config.digest.clone())
+            .build()?;
+
+        let signatures = self
+            .lock_signer()?
+            .sign(&icp.serialize()?, self.config.signature);
+        let signed = icp.sign(signatures.await?, None);
+
+        self.processor.process_own_event(&signed)?;
+
+        self.prefix = icp.event.get_prefix();
+
+        Ok(signed)
+    }
+
+    pub async fn rotate_async(&mut self) -> Result<SignedEventMessage, Error> {
+        let rotated = self.lock_signer()?.rotate();
+        rotated.await?;
+        let keys = self.lock_signer()?.public_keys(self.config.key_derivation);
+        let keys = keys.await?;
+        let state = self
+            .processor
+            .compute_state(&self.prefix)?
+            .ok_or_else(|| Error::SemanticError("There is no state".into()))?;
+        let rot = EventMsgBuilder::new(EventTypeTag::Rot)
+            .with_prefix(&self.prefix)
+            .with_sn(state.sn + 1)
+            .with_previous_event(&state.last_event_digest)
+            .with_keys(keys.current)
+            .with_next_keys(keys.next)
+            .with_threshold(&keys.threshold)
+            .with_next_threshold(&keys.next_threshold)
+            .with_witness_threshold(state.tally)
+            .with_format(self.config.format)
+            .with_derivation(self.config.digest.clone())
+            .build()?;
+
+        let signatures = self
+            .lock_signer()?
+            .sign(&rot.serialize()?, self.config.signature);
+        let rot = rot.sign(signatures.await?, None);
+
+        self.processor.process_own_event(&rot)?;
+
+        Ok(rot)
+    }
+
+    pub async fn make_ixn_async(
+        &mut self,
+        payload: Option<&str>,
+    ) -> Result<SignedEventMessage, Error> {
+        let ev = self.make_ixn_event(&self.prefix, self.payload_seals(payload))?;
+        let signatures = self
+            .lock_signer()?
+            .sign(&ev.serialize()?, self.config.signature);
+        let ixn = ev.sign(signatures.await?, None);
+
+        self.processor.process_own_event(&ixn)?;
+
+        Ok(ixn)
+    }
+
+    fn lock_signer(&self) -> Result<std::sync::MutexGuard<'_, K>, Error> {
+        self.key_manager.lock().map_err(|_| Error::MutexPoisoned)
+    }
+}
+
 // Non re-allocating random `String` generator with output length of 10 char string
 #[cfg(feature = "wallet")]
 fn generate_random_string() -> String {
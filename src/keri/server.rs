@@ -0,0 +1,312 @@
+use std::{convert::TryFrom, net::SocketAddr, sync::Arc, time::Duration};
+
+use async_std::{
+    io::{self, prelude::ReadExt, Read, WriteExt},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+};
+
+use super::witness::Witness;
+use crate::{
+    error::Error,
+    event_message::{serialization_info::SerializationInfo, signed_event_message::Message},
+    event_parsing::{message::signed_message, SignedEventData},
+    transport::{self, transport_error},
+};
+
+/// Upper bound of bytes buffered for one message with its attachments.
+const MAX_FRAME_SIZE: usize = 1 << 20;
+/// Version string starts within this many leading bytes of a message.
+const VERSION_SPAN: usize = 24;
+const VERSION_LEN: usize = 17;
+/// Time given to following attachment groups of a message.
+const ATTACHMENT_WAIT: Duration = Duration::from_millis(100);
+
+/// Returns size of message body declared by its version string, or `None`
+/// when the version string didn't arrive yet.
+fn message_size(buf: &[u8]) -> Result<Option<usize>, Error> {
+    if buf.len() < VERSION_SPAN + VERSION_LEN {
+        return Ok(None);
+    }
+    let start = buf[..VERSION_SPAN]
+        .windows(4)
+        .position(|window| window == b"KERI")
+        .ok_or_else(|| Error::DeserializeError("Missing version string".into()))?;
+    let version = std::str::from_utf8(&buf[start..start + VERSION_LEN])
+        .map_err(|_| Error::DeserializeError("Improper version string".into()))?;
+    Ok(Some(version.parse::<SerializationInfo>()?.size))
+}
+
+/// Splits first complete message off the CESR stream. Every KERI message
+/// carries attachments, so message is complete when its attachments arrived
+/// and no partial attachment follows them. Fails when stream can't be
+/// parsed, as it can't be resynchronized.
+fn next_frame(buf: &[u8]) -> Result<Option<(usize, SignedEventData)>, Error> {
+    let size = match message_size(buf)? {
+        Some(size) if buf.len() > size => size,
+        _ => return Ok(None),
+    };
+    match signed_message(buf) {
+        Ok((rest, _)) if rest.starts_with(b"-") => Ok(None),
+        Ok((rest, message)) if !message.attachments.is_empty() && buf.len() - rest.len() > size => {
+            Ok(Some((buf.len() - rest.len(), message)))
+        }
+        Ok(_) => Err(Error::DeserializeError("Missing attachment".into())),
+        Err(nom::Err::Incomplete(_)) => Ok(None),
+        Err(_) => Err(Error::DeserializeError("Improper message".into())),
+    }
+}
+
+/// Reads next message of CESR stream from `reader`. Bytes read past the
+/// message are kept in `buf` for following calls. Returns `None` when
+/// stream is closed between messages.
+///
+/// When read data ends right after attachment group, next group may still
+/// be on the way, so message is framed only after `ATTACHMENT_WAIT` passes
+/// without new data or the stream is closed.
+pub async fn read_message<R: Read + Unpin>(
+    reader: &mut R,
+    buf: &mut Vec<u8>,
+) -> Result<Option<SignedEventData>, Error> {
+    let mut chunk = [0u8; 4096];
+    loop {
+        let framed = next_frame(buf)?;
+        match framed {
+            Some((len, message)) if len < buf.len() => {
+                buf.drain(..len);
+                return Ok(Some(message));
+            }
+            Some((len, message)) => {
+                match io::timeout(ATTACHMENT_WAIT, reader.read(&mut chunk)).await {
+                    Ok(read) if read > 0 => buf.extend_from_slice(&chunk[..read]),
+                    Ok(_) => {
+                        buf.drain(..len);
+                        return Ok(Some(message));
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {
+                        buf.drain(..len);
+                        return Ok(Some(message));
+                    }
+                    Err(e) => return Err(transport_error(e)),
+                }
+                continue;
+            }
+            None => (),
+        }
+        if buf.len() > MAX_FRAME_SIZE {
+            return Err(Error::DeserializeError("Message too long".into()));
+        }
+        let read = reader.read(&mut chunk).await.map_err(transport_error)?;
+        if read == 0 {
+            return if buf.is_empty() {
+                Ok(None)
+            } else {
+                Err(Error::TransportError("stream closed within message".into()))
+            };
+        }
+        buf.extend_from_slice(&chunk[..read]);
+    }
+}
+
+/// Witness Server
+///
+/// Serves `Witness` over TCP. Every connection carries CESR stream of key
+/// events, receipts, `qry` and `rpy` messages, answered with receipts of
+/// witnessed events, replies and KELs written back to the same connection.
+/// Stream which can't be parsed gets error description as response and the
+/// connection is closed.
+pub struct WitnessServer {
+    listener: TcpListener,
+    witness: Arc<Witness>,
+}
+
+impl WitnessServer {
+    pub async fn bind<A: ToSocketAddrs>(addr: A, witness: Arc<Witness>) -> Result<Self, Error> {
+        let listener = TcpListener::bind(addr).await.map_err(transport_error)?;
+        Ok(Self { listener, witness })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        self.listener.local_addr().map_err(transport_error)
+    }
+
+    /// Serves accepted connections until the listener fails.
+    pub async fn run(self) -> Result<(), Error> {
+        let witness = self.witness;
+        transport::accept(self.listener.incoming(), |stream| {
+            Self::serve(stream, Arc::clone(&witness))
+        })
+        .await
+        .map_err(transport_error)
+    }
+
+    async fn serve(stream: TcpStream, witness: Arc<Witness>) -> Result<(), Error> {
+        let mut reader = stream.clone();
+        let mut writer = stream;
+        let mut buf = vec![];
+        loop {
+            let message = match read_message(&mut reader, &mut buf)
+                .await
+                .map_err(|e| e.to_string())
+            {
+                Ok(Some(message)) => message,
+                Ok(None) => return Ok(()),
+                Err(description) => {
+                    let _ = writer
+                        .write_all(format!("{}\n", description).as_bytes())
+                        .await;
+                    return Err(Error::TransportError(description));
+                }
+            };
+            // messages failing to process get no response
+            let response = Message::try_from(message)
+                .and_then(|message| witness.respond(message))
+                .unwrap_or_default();
+            writer.write_all(&response).await.map_err(transport_error)?;
+        }
+    }
+}
+
+#[test]
+fn test_witness_server() -> Result<(), Error> {
+    use crate::{
+        database::sled::SledEventDatabase,
+        derivation::{self_addressing::SelfAddressing, self_signing::SelfSigning},
+        event::SerializationFormats,
+        keri::Keri,
+        prefix::AttachedSignaturePrefix,
+        query::{
+            query::{QueryEvent, SignedQuery},
+            Route,
+        },
+        signer::{CryptoBox, KeyManager},
+    };
+    use async_std::task;
+    use std::sync::Mutex;
+    use tempfile::Builder;
+
+    // response is expected shortly, test shouldn't hang on missing one
+    async fn read_response(stream: &mut TcpStream, buf: &mut Vec<u8>) -> Message {
+        let message = async_std::future::timeout(Duration::from_secs(5), read_message(stream, buf))
+            .await
+            .expect("no response")
+            .unwrap()
+            .unwrap();
+        Message::try_from(message).unwrap()
+    }
+
+    let witness_root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let witness = Arc::new(Witness::new(witness_root.path())?);
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db = Arc::new(SledEventDatabase::new(root.path())?);
+    let key_manager = Arc::new(Mutex::new(CryptoBox::new()?));
+    let mut alice = Keri::new(db, Arc::clone(&key_manager))?;
+
+    task::block_on(async {
+        let server = WitnessServer::bind("127.0.0.1:0", Arc::clone(&witness)).await?;
+        let addr = server.local_addr()?;
+        task::spawn(async move { server.run().await.ok() });
+
+        let mut stream = TcpStream::connect(addr).await.map_err(transport_error)?;
+        let mut buf = vec![];
+
+        // events are receipted
+        let icp = alice.incept(Some(vec![witness.prefix.clone()]))?;
+        let ixn = alice.make_ixn(Some("data"))?;
+        let stream_events = [
+            SignedEventData::from(&icp).to_cesr()?,
+            SignedEventData::from(&ixn).to_cesr()?,
+        ]
+        .concat();
+        stream
+            .write_all(&stream_events)
+            .await
+            .map_err(transport_error)?;
+        for _ in 0..2 {
+            let rct = read_response(&mut stream, &mut buf).await;
+            alice.processor.process(rct)?;
+        }
+        assert_eq!(witness.get_receipts(alice.prefix()).len(), 2);
+
+        // queries are answered
+        let qry = QueryEvent::new_query(
+            Route::Ksn,
+            alice.prefix(),
+            SerializationFormats::JSON,
+            &SelfAddressing::Blake3_256,
+        )?;
+        let signature = key_manager.lock().unwrap().sign(&qry.serialize()?)?;
+        let qry = SignedQuery::new(
+            qry,
+            alice.prefix().clone(),
+            vec![AttachedSignaturePrefix::new(
+                SelfSigning::Ed25519Sha512,
+                signature,
+                0,
+            )],
+        );
+        // sent in two parts
+        let qry = SignedEventData::from(qry).to_cesr()?;
+        let (first, second) = qry.split_at(qry.len() - 10);
+        stream.write_all(first).await.map_err(transport_error)?;
+        stream.flush().await.map_err(transport_error)?;
+        task::sleep(Duration::from_millis(50)).await;
+        stream.write_all(second).await.map_err(transport_error)?;
+        match read_response(&mut stream, &mut buf).await {
+            Message::KeyStateNotice(rpy) => {
+                assert_eq!(rpy.reply.event.get_state()?, alice.get_state()?.unwrap())
+            }
+            _ => panic!("expected key state notice"),
+        };
+
+        // improper stream gets error and connection is closed
+        stream
+            .write_all(&[b'x'; 64])
+            .await
+            .map_err(transport_error)?;
+        let mut response = vec![];
+        async_std::future::timeout(Duration::from_secs(5), stream.read_to_end(&mut response))
+            .await
+            .expect("connection not closed")
+            .map_err(transport_error)?;
+        assert_eq!(response, b"Deserialize error: Missing version string\n");
+        Ok(())
+    })
+}
+
+#[test]
+fn test_next_frame() {
+    let stream = br#"{"v":"KERI10JSON00017e_","t":"icp","d":"ELYk-z-SuTIeDncLr6GhwVUKnv3n3F1bF18qkXNd2bpk","i":"ELYk-z-SuTIeDncLr6GhwVUKnv3n3F1bF18qkXNd2bpk","s":"0","kt":"2","k":["DSuhyBcPZEZLK-fcw5tzHn2N46wRCG_ZOoeKtWTOunRA","DVcuJOOJF1IE8svqEtrSuyQjGTd2HhfAkt9y2QkUtFJI","DT1iAhBWCkvChxNWsby2J0pJyxBIxbAtbLA0Ljx-Grh8"],"n":"E9izzBkXX76sqt0N-tfLzJeRqj0W56p4pDQ_ZqNCDpyw","bt":"0","b":[],"c":[],"a":[]}-AADAA39j08U7pcU66OPKsaPExhBuHsL5rO1Pjq5zMgt_X6jRbezevis6YBUg074ZNKAGdUwHLqvPX_kse4buuuSUpAQABphobpuQEZ6EhKLhBuwgJmIQu80ZUV1GhBL0Ht47Hsl1rJiMwE2yW7-yi8k3idw2ahlpgdd9ka9QOP9yQmMWGAQACM7yfK1b86p1H62gonh1C7MECDCFBkoH0NZRjHKAEHebvd2_LLz6cpCaqKWDhbM2Rq01f9pgyDTFNLJMxkC-fAQ"#;
+    assert_eq!(
+        next_frame(stream).unwrap().map(|(len, _)| len),
+        Some(stream.len())
+    );
+    // message or its attachments didn't arrive yet
+    assert!(next_frame(&stream[..100]).unwrap().is_none());
+    assert!(next_frame(&stream[..0x17e]).unwrap().is_none());
+    assert!(next_frame(&stream[..stream.len() - 10]).unwrap().is_none());
+
+    // attachments split at group boundary are framed with the message
+    let (body, signatures) = (&stream[..0x17e], &stream[0x17e + 4..]);
+    let first = [body, b"-AAB", &signatures[..88]].concat();
+    let second = [b"-AAC", &signatures[88..]].concat();
+    let mut reader = async_std::io::Cursor::new(first).chain(async_std::io::Cursor::new(second));
+    let message = async_std::task::block_on(read_message(&mut reader, &mut vec![]))
+        .unwrap()
+        .unwrap();
+    assert_eq!(message.attachments.len(), 2);
+
+    // stream which can't be parsed
+    assert!(next_frame(&[b'x'; 64]).is_err());
+    let mut corrupted = stream.to_vec();
+    corrupted[50] = b'"';
+    assert!(next_frame(&corrupted).is_err());
+    // attachments longer than allowed
+    let mut endless = stream[..0x17e].to_vec();
+    endless.extend([b'-'; 1]);
+    endless.extend(std::iter::repeat_n(b'x', 2 * MAX_FRAME_SIZE));
+    let result = async_std::task::block_on(read_message(
+        &mut async_std::io::Cursor::new(endless),
+        &mut vec![],
+    ));
+    assert!(matches!(result, Err(Error::DeserializeError(_))));
+}
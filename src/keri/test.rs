@@ -251,11 +251,11 @@ fn test_qry_rpy() -> Result<(), Error> {
     match rep {
         ReplyType::Rep(rep) => {
             assert_eq!(
-                &rep.reply.event.get_state(),
+                &rep.reply.event.get_state()?,
                 &alice.get_state().unwrap().unwrap()
             )
         }
-        ReplyType::Kel(_) => assert!(false),
+        _ => assert!(false),
     }
 
     Ok(())
@@ -344,3 +344,1798 @@ pub fn test_key_state_notice() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[test]
+fn test_multi_key_controller() -> Result<(), Error> {
+    use crate::{
+        derivation::basic::Basic,
+        event::sections::threshold::SignatureThreshold,
+        event_parsing::SignedEventData,
+        prefix::IdentifierPrefix,
+        signer::{key_ring::KeyRing, KeyManager},
+    };
+    use tempfile::Builder;
+
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db_alice = Arc::new(SledEventDatabase::new(root.path()).unwrap());
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db_bob = Arc::new(SledEventDatabase::new(root.path()).unwrap());
+
+    let key_ring = KeyRing::new(
+        &[Basic::Ed25519, Basic::ECDSAsecp256k1, Basic::Ed25519],
+        SignatureThreshold::single_weighted(vec![(1, 2), (1, 2), (1, 2)]),
+        &[Basic::Ed25519, Basic::Ed25519],
+        SignatureThreshold::Simple(2),
+    )?;
+    let mut alice = Keri::new(db_alice, Arc::new(Mutex::new(key_ring)))?;
+    let bob = Keri::new(
+        db_bob,
+        Arc::new(Mutex::new(crate::signer::CryptoBox::new()?)),
+    )?;
+
+    let icp = alice.incept(None)?;
+    assert!(matches!(
+        alice.prefix(),
+        IdentifierPrefix::SelfAddressing(_)
+    ));
+    let indexes: Vec<_> = icp.signatures.iter().map(|sig| sig.index).collect();
+    assert_eq!(indexes, vec![0, 1, 2]);
+    let state = alice.get_state()?.unwrap();
+    assert_eq!(state.current.public_keys.len(), 3);
+    assert_eq!(
+        state.current.public_keys[1].derivation,
+        Basic::ECDSAsecp256k1
+    );
+
+    let ixn = alice.make_ixn(Some("data"))?;
+    let rot = alice.rotate()?;
+    assert_eq!(rot.signatures.len(), 2);
+    let state = alice.get_state()?.unwrap();
+    assert_eq!(state.sn, 2);
+    assert_eq!(
+        state.current.public_keys,
+        alice
+            .key_manager()
+            .lock()
+            .unwrap()
+            .public_keys(Basic::Ed25519)?
+    );
+    assert_eq!(state.current.threshold, SignatureThreshold::Simple(2));
+
+    // other controller accepts KEL signed with all keys
+    let kel = [icp, ixn, rot]
+        .iter()
+        .map(|ev| SignedEventData::from(ev).to_cesr())
+        .collect::<Result<Vec<_>, _>>()?
+        .concat();
+    for ev in signed_event_stream(&kel).unwrap().1 {
+        bob.processor.process(Message::try_from(ev)?)?;
+    }
+    assert_eq!(
+        bob.get_state_for_prefix(alice.prefix())?,
+        alice.get_state()?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_keri_config() -> Result<(), Error> {
+    use crate::{
+        derivation::{basic::Basic, self_addressing::SelfAddressing, self_signing::SelfSigning},
+        event::SerializationFormats,
+        event_parsing::SignedEventData,
+        keri::KeriConfig,
+        prefix::IdentifierPrefix,
+        signer::{CryptoBox, KeyManager},
+    };
+    use tempfile::Builder;
+
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db_alice = Arc::new(SledEventDatabase::new(root.path()).unwrap());
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db_bob = Arc::new(SledEventDatabase::new(root.path()).unwrap());
+
+    let config = KeriConfig {
+        key_derivation: Basic::Ed25519,
+        signature: SelfSigning::Ed25519Sha512,
+        digest: SelfAddressing::SHA3_256,
+        format: SerializationFormats::CBOR,
+    };
+    // signatures of Ed25519 keys can't be labeled as ECDSA ones
+    let mismatched = KeriConfig {
+        signature: SelfSigning::ECDSAsecp256k1Sha256,
+        ..config.clone()
+    };
+    assert!(Keri::new(
+        Arc::clone(&db_alice),
+        Arc::new(Mutex::new(CryptoBox::new()?))
+    )?
+    .with_config(mismatched)
+    .is_err());
+    // ECDSA keys can't be produced by Ed25519 key manager
+    let unsupported = KeriConfig {
+        key_derivation: Basic::ECDSAsecp256k1,
+        signature: SelfSigning::ECDSAsecp256k1Sha256,
+        ..config.clone()
+    };
+    assert!(Keri::new(
+        Arc::clone(&db_alice),
+        Arc::new(Mutex::new(CryptoBox::new()?))
+    )?
+    .with_config(unsupported)
+    .is_err());
+    assert!(CryptoBox::new()?
+        .public_keys(Basic::ECDSAsecp256k1)
+        .is_err());
+
+    let mut alice =
+        Keri::new(db_alice, Arc::new(Mutex::new(CryptoBox::new()?)))?.with_config(config)?;
+    let bob = Keri::new(db_bob, Arc::new(Mutex::new(CryptoBox::new()?)))?;
+
+    let icp = alice.incept(None)?;
+    let ixn = alice.make_ixn(Some("data"))?;
+    let rot = alice.rotate()?;
+    for ev in [&icp, &ixn, &rot].iter() {
+        assert_eq!(
+            ev.event_message.serialization_info.kind,
+            SerializationFormats::CBOR
+        );
+    }
+    assert!(matches!(alice.prefix(), IdentifierPrefix::Basic(_)));
+    let state = alice.get_state()?.unwrap();
+    assert_eq!(state.sn, 2);
+    assert_eq!(state.last_event_digest.derivation, SelfAddressing::SHA3_256);
+
+    let kel = [icp, ixn, rot]
+        .iter()
+        .map(|ev| SignedEventData::from(ev).to_cesr())
+        .collect::<Result<Vec<_>, _>>()?
+        .concat();
+    for ev in signed_event_stream(&kel).unwrap().1 {
+        bob.processor.process(Message::try_from(ev)?)?;
+    }
+    assert_eq!(bob.get_state_for_prefix(alice.prefix())?, Some(state));
+
+    Ok(())
+}
+
+#[test]
+fn test_group_identifier() -> Result<(), Error> {
+    use crate::{
+        event::sections::threshold::SignatureThreshold,
+        event_message::signed_event_message::SignedEventMessage,
+        keri::group::GroupMember,
+        prefix::{AttachedSignaturePrefix, IdentifierPrefix},
+        signer::{CryptoBox, KeyManager},
+    };
+    use tempfile::Builder;
+
+    // 3 of 5 group
+    let dirs = (0..5)
+        .map(|_| Builder::new().prefix("test-db").tempdir().unwrap())
+        .collect::<Vec<_>>();
+    let participants = dirs
+        .iter()
+        .map(|dir| {
+            let db = Arc::new(SledEventDatabase::new(dir.path())?);
+            Keri::new(db, Arc::new(Mutex::new(CryptoBox::new()?)))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    let members = participants
+        .iter()
+        .map(|p| p.group_member())
+        .collect::<Result<Vec<GroupMember>, Error>>()?;
+    let threshold = SignatureThreshold::Simple(3);
+
+    let distribute = |signed: &SignedEventMessage, assembler: usize| -> Result<(), Error> {
+        for (i, p) in participants.iter().enumerate() {
+            if i != assembler {
+                p.processor
+                    .process(Message::Event(Box::new(signed.clone())))?;
+            }
+        }
+        Ok(())
+    };
+
+    let icp =
+        participants[0].make_group_icp(&members, threshold.clone(), threshold.clone(), None)?;
+    let group = icp.event.get_prefix();
+    assert!(matches!(group, IdentifierPrefix::SelfAddressing(_)));
+    let partials = participants[..3]
+        .iter()
+        .map(|p| p.sign_group_event(&icp))
+        .collect::<Result<Vec<_>, _>>()?;
+    assert!(matches!(
+        participants[0].finalize_group_event(&partials[..2]),
+        Err(Error::NotEnoughSigsError)
+    ));
+    let signed = participants[0].finalize_group_event(&partials)?;
+    distribute(&signed, 0)?;
+
+    // non member can't sign
+    let outsider_root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let outsider = Keri::new(
+        Arc::new(SledEventDatabase::new(outsider_root.path())?),
+        Arc::new(Mutex::new(CryptoBox::new()?)),
+    )?;
+    assert!(outsider.sign_group_event(&icp).is_err());
+
+    let ixn = participants[4].make_group_ixn(&group, Some("payment"))?;
+    let partials = participants[2..]
+        .iter()
+        .map(|p| p.sign_group_event(&ixn))
+        .collect::<Result<Vec<_>, _>>()?;
+    // bad partial arriving first doesn't push out valid signature
+    let forged = SignedEventMessage::new(
+        &ixn,
+        vec![AttachedSignaturePrefix {
+            index: partials[0].signatures[0].index,
+            ..partials[1].signatures[0].clone()
+        }],
+        None,
+    );
+    let partials = [vec![forged], partials].concat();
+    let signed = participants[4].finalize_group_event(&partials)?;
+    assert_eq!(signed.signatures.len(), 3);
+    distribute(&signed, 4)?;
+
+    // every member rotates its keys before group rotation
+    for p in participants.iter() {
+        p.key_manager().lock().unwrap().rotate()?;
+    }
+    let members = participants
+        .iter()
+        .map(|p| p.group_member())
+        .collect::<Result<Vec<GroupMember>, Error>>()?;
+    let rot = participants[1].make_group_rot(&group, &members, threshold.clone(), threshold)?;
+    let partials = [0, 2, 4]
+        .iter()
+        .map(|i| participants[*i].sign_group_event(&rot))
+        .collect::<Result<Vec<_>, _>>()?;
+    let signed = participants[1].finalize_group_event(&partials)?;
+    let indexes: Vec<_> = signed.signatures.iter().map(|sig| sig.index).collect();
+    assert_eq!(indexes, vec![0, 2, 4]);
+    distribute(&signed, 1)?;
+
+    let state = participants[0].get_state_for_prefix(&group)?.unwrap();
+    assert_eq!(state.sn, 2);
+    assert_eq!(
+        state.current.public_keys,
+        members
+            .iter()
+            .flat_map(|m| m.keys.clone())
+            .collect::<Vec<_>>()
+    );
+    for p in participants.iter() {
+        assert_eq!(p.get_state_for_prefix(&group)?, Some(state.clone()));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_delegation() -> Result<(), Error> {
+    use crate::{
+        derivation::{basic::Basic, self_signing::SelfSigning},
+        event::sections::seal::Seal,
+        event_message::{event_msg_builder::EventMsgBuilder, EventTypeTag},
+        keys::PublicKey,
+        signer::{CryptoBox, KeyManager},
+    };
+    use tempfile::Builder;
+
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db_delegator = Arc::new(SledEventDatabase::new(root.path()).unwrap());
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db_delegate = Arc::new(SledEventDatabase::new(root.path()).unwrap());
+
+    let mut delegator = Keri::new(db_delegator, Arc::new(Mutex::new(CryptoBox::new()?)))?;
+    let mut delegate = Keri::new(db_delegate, Arc::new(Mutex::new(CryptoBox::new()?)))?;
+    delegator.incept(None)?;
+
+    let request = delegate.incept_delegated(delegator.prefix())?;
+    // not approved event can't be accepted
+    assert!(delegate
+        .processor
+        .process(Message::Event(Box::new(request.clone())))
+        .is_err());
+
+    let source_seal = delegator.approve_delegation(&request)?;
+    assert_eq!(source_seal.sn, 1);
+    let anchor = delegator
+        .processor
+        .get_event_at_sn(delegator.prefix(), 1)?
+        .unwrap()
+        .signed_event_message;
+    assert!(matches!(
+        anchor.event_message.event.get_event_data(),
+        crate::event::event_data::EventData::Ixn(ixn) if matches!(ixn.data[0], Seal::Event(_))
+    ));
+
+    // delegate needs delegator's KEL to verify the anchor
+    for ev in delegator
+        .db()
+        .get_kel_finalized_events(delegator.prefix())
+        .unwrap()
+    {
+        delegate
+            .processor
+            .process(Message::Event(Box::new(ev.signed_event_message)))?;
+    }
+    let dip = delegate.finalize_delegation(&request, source_seal)?;
+    assert_eq!(delegate.prefix(), &dip.event_message.event.get_prefix());
+
+    let state = delegate.get_state()?.unwrap();
+    assert_eq!(state.delegator.as_ref(), Some(delegator.prefix()));
+    assert_eq!(
+        delegator.get_state_for_prefix(delegate.prefix())?,
+        Some(state)
+    );
+
+    // only delegator can approve
+    let other = delegate.incept_delegated(delegate.prefix())?;
+    assert!(delegator.approve_delegation(&other).is_err());
+
+    // witnessed delegate is approved before its witnesses receipt it
+    let km = CryptoBox::new()?;
+    let dip = EventMsgBuilder::new(EventTypeTag::Dip)
+        .with_delegator(delegator.prefix())
+        .with_keys(km.public_keys(Basic::Ed25519)?)
+        .with_next_keys(km.next_public_keys(Basic::Ed25519)?)
+        .with_witness_list(&[Basic::Ed25519.derive(PublicKey::new(vec![0; 32]))])
+        .with_witness_threshold(1)
+        .build()?;
+    let request = dip.sign(
+        km.sign_indexed(&dip.serialize()?, SelfSigning::Ed25519Sha512)?,
+        None,
+    );
+    let source_seal = delegator.approve_delegation(&request)?;
+    assert_eq!(source_seal.sn, 2);
+    assert_eq!(
+        delegator.get_state_for_prefix(&dip.event.get_prefix())?,
+        None
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_rotate_witnesses() -> Result<(), Error> {
+    use crate::{
+        derivation::{basic::Basic, self_signing::SelfSigning},
+        event_message::{event_msg_builder::EventMsgBuilder, EventTypeTag},
+        keys::PublicKey,
+        prefix::BasicPrefix,
+        signer::{CryptoBox, KeyManager},
+    };
+    use tempfile::Builder;
+
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db = Arc::new(SledEventDatabase::new(root.path()).unwrap());
+    let mut keri = Keri::new(db, Arc::new(Mutex::new(CryptoBox::new()?)))?;
+    let witnesses: Vec<BasicPrefix> = (0..4u8)
+        .map(|i| Basic::Ed25519.derive(PublicKey::new(vec![i; 32])))
+        .collect();
+    keri.incept(Some(witnesses[..2].to_vec()))?;
+
+    keri.rotate_witnesses(&witnesses[2..], &[], 3)?;
+    let state = keri.get_state()?.unwrap();
+    assert_eq!(state.witnesses, witnesses);
+    assert_eq!(state.tally, 3);
+
+    // retire first witness
+    keri.rotate_witnesses(&[], &witnesses[..1], 2)?;
+    let state = keri.get_state()?.unwrap();
+    assert_eq!(state.witnesses, witnesses[1..].to_vec());
+
+    // plain rotation keeps witnesses and tally
+    keri.rotate()?;
+    let state = keri.get_state()?.unwrap();
+    assert_eq!(state.witnesses, witnesses[1..].to_vec());
+    assert_eq!(state.tally, 2);
+
+    let next = keri.key_manager().lock().unwrap().next_public_key()?;
+    // removing absent witness
+    assert!(keri.rotate_witnesses(&[], &witnesses[..1], 1).is_err());
+    // adding and removing the same witness
+    assert!(keri
+        .rotate_witnesses(&witnesses[1..2], &witnesses[1..2], 1)
+        .is_err());
+    // adding present witness
+    assert!(keri.rotate_witnesses(&witnesses[1..2], &[], 1).is_err());
+    // infeasible tally
+    assert!(keri.rotate_witnesses(&[], &witnesses[1..2], 3).is_err());
+    // keys weren't rotated by rejected requests
+    assert_eq!(keri.key_manager().lock().unwrap().next_public_key()?, next);
+    assert_eq!(keri.get_state()?.unwrap().sn, 3);
+
+    // rotation event pruning absent witness is rejected by processing
+    let km = keri.key_manager();
+    let mut km = km.lock().unwrap();
+    km.rotate()?;
+    let rot = EventMsgBuilder::new(EventTypeTag::Rot)
+        .with_prefix(keri.prefix())
+        .with_sn(state.sn + 1)
+        .with_previous_event(&state.last_event_digest)
+        .with_keys(km.public_keys(Basic::Ed25519)?)
+        .with_next_keys(km.next_public_keys(Basic::Ed25519)?)
+        .with_witness_to_remove(&witnesses[..1])
+        .build()?;
+    let rot = rot.sign(
+        km.sign_indexed(&rot.serialize()?, SelfSigning::Ed25519Sha512)?,
+        None,
+    );
+    assert!(keri
+        .processor
+        .process(Message::Event(Box::new(rot)))
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_configuration_traits() -> Result<(), Error> {
+    use crate::{
+        derivation::basic::Basic, event::sections::configuration::ConfigurationTrait,
+        keys::PublicKey, signer::CryptoBox,
+    };
+    use tempfile::Builder;
+
+    let new_keri = || -> Result<_, Error> {
+        let root = Builder::new().prefix("test-db").tempdir().unwrap();
+        let db = Arc::new(SledEventDatabase::new(root.path())?);
+        Ok((
+            root,
+            Keri::new(db, Arc::new(Mutex::new(CryptoBox::new()?)))?,
+        ))
+    };
+
+    // establishment only
+    let (_root, mut root_of_trust) = new_keri()?;
+    root_of_trust.incept_with_configuration(None, &[ConfigurationTrait::EstablishmentOnly])?;
+    assert!(root_of_trust.make_ixn(Some("data")).is_err());
+    root_of_trust.rotate()?;
+    let state = root_of_trust.get_state()?.unwrap();
+    assert_eq!(state.sn, 1);
+    assert_eq!(state.config, vec![ConfigurationTrait::EstablishmentOnly]);
+    #[cfg(feature = "query")]
+    {
+        use crate::{event::SerializationFormats, query::key_state_notice::KeyStateNotice};
+        let ksn = KeyStateNotice::new_ksn(state, SerializationFormats::JSON);
+        assert!(String::from_utf8(serde_json::to_vec(&ksn)?)
+            .unwrap()
+            .contains(r#""c":["EO"]"#));
+    }
+
+    // no backers
+    let witness = Basic::Ed25519.derive(PublicKey::new(vec![0; 32]));
+    let (_root, mut no_backers) = new_keri()?;
+    assert!(no_backers
+        .incept_with_configuration(
+            Some(vec![witness.clone()]),
+            &[ConfigurationTrait::NoBackers]
+        )
+        .is_err());
+    no_backers.incept_with_configuration(None, &[ConfigurationTrait::NoBackers])?;
+    assert!(no_backers.rotate_witnesses(&[witness], &[], 0).is_err());
+    assert_eq!(no_backers.get_state()?.unwrap().sn, 0);
+
+    // do not delegate
+    let (_root, mut delegator) = new_keri()?;
+    let (_root, delegate) = new_keri()?;
+    delegator.incept_with_configuration(None, &[ConfigurationTrait::DoNotDelegate])?;
+    let request = delegate.incept_delegated(delegator.prefix())?;
+    assert!(delegator.approve_delegation(&request).is_err());
+    assert_eq!(delegator.get_state()?.unwrap().sn, 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_abandonment() -> Result<(), Error> {
+    use crate::{event_parsing::SignedEventData, signer::CryptoBox};
+    use tempfile::Builder;
+
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db_alice = Arc::new(SledEventDatabase::new(root.path()).unwrap());
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db_bob = Arc::new(SledEventDatabase::new(root.path()).unwrap());
+
+    let mut alice = Keri::new(db_alice, Arc::new(Mutex::new(CryptoBox::new()?)))?;
+    let bob = Keri::new(db_bob, Arc::new(Mutex::new(CryptoBox::new()?)))?;
+
+    let icp = alice.incept(None)?;
+    let ixn = alice.make_ixn(Some("data"))?;
+    let rot = alice.abandon()?;
+    let state = alice.get_state()?.unwrap();
+    assert_eq!(state.sn, 2);
+    assert_eq!(state.current.threshold_key_digest, None);
+    assert!(state.is_abandoned());
+
+    assert!(matches!(
+        alice.make_ixn(Some("more data")),
+        Err(Error::AbandonedIdentifierError)
+    ));
+    assert!(matches!(
+        alice.rotate(),
+        Err(Error::AbandonedIdentifierError)
+    ));
+    assert!(matches!(
+        alice.abandon(),
+        Err(Error::AbandonedIdentifierError)
+    ));
+    assert_eq!(alice.get_state()?.unwrap().sn, 2);
+
+    let kel = [icp, ixn, rot]
+        .iter()
+        .map(|ev| SignedEventData::from(ev).to_cesr())
+        .collect::<Result<Vec<_>, _>>()?
+        .concat();
+    for ev in signed_event_stream(&kel).unwrap().1 {
+        bob.processor.process(Message::try_from(ev)?)?;
+    }
+    assert!(bob
+        .get_state_for_prefix(alice.prefix())?
+        .unwrap()
+        .is_abandoned());
+
+    Ok(())
+}
+
+#[test]
+fn test_nontransferable_and_self_signing_identifiers() -> Result<(), Error> {
+    use crate::{
+        derivation::{basic::Basic, self_addressing::SelfAddressing, self_signing::SelfSigning},
+        event::{event_data::EventData, SerializationFormats},
+        event_message::{event_msg_builder::EventMsgBuilder, EventTypeTag},
+        event_parsing::SignedEventData,
+        prefix::{AttachedSignaturePrefix, IdentifierPrefix, SelfSigningPrefix},
+        signer::{CryptoBox, KeyManager},
+    };
+    use tempfile::Builder;
+
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db_alice = Arc::new(SledEventDatabase::new(root.path()).unwrap());
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db_bob = Arc::new(SledEventDatabase::new(root.path()).unwrap());
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db_bot = Arc::new(SledEventDatabase::new(root.path()).unwrap());
+
+    let bob = Keri::new(db_bob, Arc::new(Mutex::new(CryptoBox::new()?)))?;
+
+    // ephemeral non-transferable identifier
+    let mut bot = Keri::new(db_bot, Arc::new(Mutex::new(CryptoBox::new()?)))?;
+    let icp = bot.incept_nontransferable()?;
+    assert!(matches!(
+        bot.prefix(),
+        IdentifierPrefix::Basic(bp) if bp.derivation == Basic::Ed25519NT
+    ));
+    assert_eq!(icp.event_message.event.get_sn(), 0);
+    assert!(bot.make_ixn(None).is_err());
+    assert!(bot.rotate().is_err());
+    bob.processor
+        .process(Message::Event(Box::new(icp.clone())))?;
+    assert_eq!(bob.get_state_for_prefix(bot.prefix())?, bot.get_state()?);
+
+    // non-transferable key can't commit to next keys
+    let km = CryptoBox::new()?;
+    let icp = EventMsgBuilder::new(EventTypeTag::Icp)
+        .with_keys(vec![Basic::Ed25519NT.derive(km.public_key()?)])
+        .with_next_keys(vec![Basic::Ed25519.derive(km.next_public_key()?)])
+        .build()?;
+    let signature = km.sign(&icp.serialize()?)?;
+    let icp = icp.sign(
+        vec![AttachedSignaturePrefix::new(
+            SelfSigning::Ed25519Sha512,
+            signature,
+            0,
+        )],
+        None,
+    );
+    assert!(bob
+        .processor
+        .process(Message::Event(Box::new(icp)))
+        .is_err());
+
+    // self-signing identifier
+    let mut alice = Keri::new(db_alice, Arc::new(Mutex::new(CryptoBox::new()?)))?;
+    let icp = alice.incept_self_signing(None)?;
+    assert!(matches!(alice.prefix(), IdentifierPrefix::SelfSigning(_)));
+    let ixn = alice.make_ixn(Some("data"))?;
+    let rot = alice.rotate()?;
+    assert_eq!(alice.get_state()?.unwrap().sn, 2);
+
+    // prefix signature must be made over inception data
+    let icp_data = match icp.event_message.event.get_event_data() {
+        EventData::Icp(icp_data) => icp_data,
+        _ => unreachable!(),
+    };
+    let forged = icp_data.incept_self_signing(
+        SelfSigningPrefix::new(SelfSigning::Ed25519Sha512, km.sign(b"other data")?),
+        SelfAddressing::Blake3_256,
+        SerializationFormats::JSON,
+    )?;
+    let signature = km.sign(&forged.serialize()?)?;
+    let forged = forged.sign(
+        vec![AttachedSignaturePrefix::new(
+            SelfSigning::Ed25519Sha512,
+            signature,
+            0,
+        )],
+        None,
+    );
+    assert!(bob
+        .processor
+        .process(Message::Event(Box::new(forged)))
+        .is_err());
+
+    let kel = [icp, ixn, rot]
+        .iter()
+        .map(|ev| SignedEventData::from(ev).to_cesr())
+        .collect::<Result<Vec<_>, _>>()?
+        .concat();
+    for ev in signed_event_stream(&kel).unwrap().1 {
+        bob.processor.process(Message::try_from(ev)?)?;
+    }
+    assert_eq!(
+        bob.get_state_for_prefix(alice.prefix())?,
+        alice.get_state()?
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "query")]
+#[test]
+fn test_witness_receipts() -> Result<(), Error> {
+    use crate::{keri::witness::Witness, signer::CryptoBox};
+    use tempfile::Builder;
+
+    let witness_root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let witness = Witness::new(witness_root.path())?;
+    assert!(!witness.prefix.is_transferable());
+
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db = Arc::new(SledEventDatabase::new(root.path()).unwrap());
+    let mut alice = Keri::new(db, Arc::new(Mutex::new(CryptoBox::new()?)))?;
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db = Arc::new(SledEventDatabase::new(root.path()).unwrap());
+    let mut bob = Keri::new(db, Arc::new(Mutex::new(CryptoBox::new()?)))?;
+
+    // events of designating controller are receipted
+    let icp = alice.incept(Some(vec![witness.prefix.clone()]))?;
+    let rct = witness.process_event(&icp)?.unwrap();
+    assert_eq!(rct.couplets[0].0, witness.prefix);
+    alice
+        .processor
+        .process(Message::NontransferableRct(rct.clone()))?;
+    assert_eq!(
+        alice
+            .processor
+            .db
+            .get_receipts_nt(alice.prefix())
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![rct.clone()]
+    );
+
+    // resent event gets the same receipt
+    assert_eq!(witness.process_event(&icp)?, Some(rct.clone()));
+
+    let ixn = alice.make_ixn(None)?;
+    assert!(witness.process_event(&ixn)?.is_some());
+    assert_eq!(witness.get_receipts(alice.prefix()).len(), 2);
+
+    // events of other identifiers are only processed
+    let bob_icp = bob.incept(None)?;
+    assert!(witness.process_event(&bob_icp)?.is_none());
+    assert!(witness.get_receipts(bob.prefix()).is_empty());
+    assert_eq!(
+        witness.processor.compute_state(bob.prefix())?,
+        bob.get_state()?
+    );
+
+    // no receipts after witness is removed
+    let rot = alice.rotate_witnesses(&[], std::slice::from_ref(&witness.prefix), 0)?;
+    assert!(witness.process_event(&rot)?.is_none());
+    assert_eq!(witness.get_receipts(alice.prefix()).len(), 2);
+
+    Ok(())
+}
+
+#[cfg(feature = "query")]
+#[test]
+fn test_witness_receipt_exchange() -> Result<(), Error> {
+    use crate::{
+        event_message::signed_event_message::SignedNontransferableReceipt, keri::witness::Witness,
+        processor::EventProcessor, signer::CryptoBox,
+    };
+    use tempfile::Builder;
+
+    let witness_roots: Vec<_> = (0..3)
+        .map(|_| Builder::new().prefix("test-db").tempdir().unwrap())
+        .collect();
+    let witnesses = witness_roots
+        .iter()
+        .map(|root| Witness::new(root.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db = Arc::new(SledEventDatabase::new(root.path()).unwrap());
+    let mut alice = Keri::new(db, Arc::new(Mutex::new(CryptoBox::new()?)))?;
+    let icp = alice.incept(Some(vec![
+        witnesses[0].prefix.clone(),
+        witnesses[1].prefix.clone(),
+    ]))?;
+    let receipts = witnesses[..2]
+        .iter()
+        .map(|witness| Ok(witness.process_event(&icp)?.unwrap()))
+        .collect::<Result<Vec<_>, Error>>()?;
+    witnesses[2].process_event(&icp)?;
+
+    // first witness gets receipts of all witnesses, including its own
+    witnesses[0].process_receipts(&receipts)?;
+    let couplets: Vec<_> = witnesses[0]
+        .get_receipts(alice.prefix())
+        .into_iter()
+        .flat_map(|rct| rct.couplets)
+        .collect();
+    assert_eq!(couplets.len(), 2);
+
+    // receipts of not designated witness are rejected
+    let not_designated = SignedNontransferableReceipt::new(
+        &receipts[0].body,
+        vec![(
+            witnesses[2].prefix.clone(),
+            receipts[0].couplets[0].1.clone(),
+        )],
+    );
+    assert!(witnesses[1].process_receipts(&[not_designated]).is_err());
+    // as well as invalid signatures
+    let forged = SignedNontransferableReceipt::new(
+        &receipts[0].body,
+        vec![(
+            witnesses[0].prefix.clone(),
+            receipts[1].couplets[0].1.clone(),
+        )],
+    );
+    assert!(witnesses[1]
+        .process_receipts(std::slice::from_ref(&forged))
+        .is_err());
+    assert_eq!(witnesses[1].get_receipts(alice.prefix()).len(), 1);
+    // invalid receipt rejects the whole batch
+    assert!(witnesses[1]
+        .process_receipts(&[receipts[0].clone(), forged])
+        .is_err());
+    assert_eq!(witnesses[1].get_receipts(alice.prefix()).len(), 1);
+
+    // KEL served by witness carries all receipts
+    let kerl = witnesses[0]
+        .processor
+        .get_kerl_with_receipts(alice.prefix())?
+        .unwrap();
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let bob = EventProcessor::new(Arc::new(SledEventDatabase::new(root.path()).unwrap()));
+    for msg in signed_event_stream(&kerl).unwrap().1 {
+        bob.process(Message::try_from(msg)?)?;
+    }
+    assert_eq!(bob.compute_state(alice.prefix())?, alice.get_state()?);
+    let couplets: Vec<_> = bob
+        .db
+        .get_receipts_nt(alice.prefix())
+        .unwrap()
+        .flat_map(|rct| rct.couplets)
+        .collect();
+    assert_eq!(
+        couplets,
+        [receipts[0].couplets.clone(), receipts[1].couplets.clone()].concat()
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "query")]
+#[test]
+fn test_witness_agreement() -> Result<(), Error> {
+    use crate::{
+        derivation::self_signing::SelfSigning,
+        event_message::signed_event_message::{SignedEventMessage, SignedNontransferableReceipt},
+        keri::witness::Witness,
+        prefix::{AttachedSignaturePrefix, IdentifierPrefix},
+        processor::EventProcessor,
+        signer::{CryptoBox, KeyManager},
+    };
+    use tempfile::Builder;
+
+    let witness_roots: Vec<_> = (0..3)
+        .map(|_| Builder::new().prefix("test-db").tempdir().unwrap())
+        .collect();
+    let witnesses = witness_roots
+        .iter()
+        .map(|root| Witness::new(root.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db = Arc::new(SledEventDatabase::new(root.path()).unwrap());
+    let mut alice = Keri::new(db, Arc::new(Mutex::new(CryptoBox::new()?)))?;
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let bob = EventProcessor::new(Arc::new(SledEventDatabase::new(root.path()).unwrap()));
+
+    let witness_and_bob = |event: &SignedEventMessage| -> Result<(), Error> {
+        // bob escrows event until witnesses receipt it
+        match bob.process(Message::Event(Box::new(event.clone()))) {
+            Ok(_) | Err(Error::NotEnoughReceiptsError) => (),
+            Err(e) => return Err(e),
+        };
+        for witness in &witnesses {
+            if let Some(rct) = witness.process_event(event)? {
+                bob.process(Message::NontransferableRct(rct))?;
+            }
+        }
+        Ok(())
+    };
+    let last_digest = |id: &IdentifierPrefix| -> Result<_, Error> {
+        Ok(bob.compute_state(id)?.unwrap().last_event_digest)
+    };
+
+    // without tally every event is agreed
+    let icp = alice.incept(Some(vec![
+        witnesses[0].prefix.clone(),
+        witnesses[1].prefix.clone(),
+    ]))?;
+    witness_and_bob(&icp)?;
+    assert!(bob.has_agreement(alice.prefix(), 0)?);
+    assert!(!bob.has_agreement(alice.prefix(), 1)?);
+
+    let rot = alice.rotate_witnesses(&[], &[], 2)?;
+    witness_and_bob(&rot)?;
+    assert!(bob.has_agreement(alice.prefix(), 1)?);
+    assert_eq!(last_digest(alice.prefix())?, rot.event_message.get_digest());
+
+    let conflicting = alice.make_ixn_event(alice.prefix(), alice.payload_seals(Some("second")))?;
+
+    // event receipted by one of two witnesses waits in escrow
+    let ixn = alice.make_ixn(Some("first"))?;
+    assert!(matches!(
+        bob.process(Message::Event(Box::new(ixn.clone()))),
+        Err(Error::NotEnoughReceiptsError)
+    ));
+    let rct = witnesses[0].process_event(&ixn)?.unwrap();
+    bob.process(Message::NontransferableRct(rct.clone()))?;
+    assert!(!bob.has_agreement(alice.prefix(), 2)?);
+    assert_eq!(last_digest(alice.prefix())?, rot.event_message.get_digest());
+
+    // receipts of not designated witnesses are rejected
+    let not_designated = SignedNontransferableReceipt::new(
+        &rct.body,
+        vec![(witnesses[2].prefix.clone(), rct.couplets[0].1.clone())],
+    );
+    assert!(bob
+        .process(Message::NontransferableRct(not_designated))
+        .is_err());
+
+    // conflicting event is escrowed too, without replacing any event
+    let signature = alice
+        .key_manager()
+        .lock()
+        .unwrap()
+        .sign(&conflicting.serialize()?)?;
+    let conflicting = conflicting.sign(
+        vec![AttachedSignaturePrefix::new(
+            SelfSigning::Ed25519Sha512,
+            signature,
+            0,
+        )],
+        None,
+    );
+    assert!(matches!(
+        bob.process(Message::Event(Box::new(conflicting.clone()))),
+        Err(Error::NotEnoughReceiptsError)
+    ));
+    assert_eq!(last_digest(alice.prefix())?, rot.event_message.get_digest());
+
+    // event agreed first is logged, the other one is duplicitous
+    let rct = witnesses[1].process_event(&ixn)?.unwrap();
+    bob.process(Message::NontransferableRct(rct))?;
+    assert!(bob.has_agreement(alice.prefix(), 2)?);
+    assert_eq!(last_digest(alice.prefix())?, ixn.event_message.get_digest());
+    assert!(bob
+        .db
+        .get_duplicious_events(alice.prefix())
+        .unwrap()
+        .any(|event| event.signed_event_message == conflicting));
+
+    // agreed event can't be superseded
+    assert!(bob.process(Message::Event(Box::new(conflicting))).is_err());
+    assert_eq!(last_digest(alice.prefix())?, ixn.event_message.get_digest());
+
+    Ok(())
+}
+
+#[cfg(feature = "query")]
+#[test]
+fn test_watcher() -> Result<(), Error> {
+    use crate::{
+        derivation::self_signing::SelfSigning,
+        event_parsing::SignedEventData,
+        keri::{
+            watcher::{DuplicityAlert, Watcher},
+            witness::Witness,
+        },
+        prefix::AttachedSignaturePrefix,
+        signer::{CryptoBox, KeyManager},
+    };
+    use tempfile::Builder;
+
+    let witness_roots: Vec<_> = (0..2)
+        .map(|_| Builder::new().prefix("test-db").tempdir().unwrap())
+        .collect();
+    let witnesses = witness_roots
+        .iter()
+        .map(|root| Witness::new(root.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let watcher_root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let mut watcher = Watcher::new(watcher_root.path())?;
+
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db = Arc::new(SledEventDatabase::new(root.path()).unwrap());
+    let mut alice = Keri::new(db, Arc::new(Mutex::new(CryptoBox::new()?)))?;
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db = Arc::new(SledEventDatabase::new(root.path()).unwrap());
+    let mut bob = Keri::new(db, Arc::new(Mutex::new(CryptoBox::new()?)))?;
+
+    let icp = alice.incept(Some(witnesses.iter().map(|w| w.prefix.clone()).collect()))?;
+    let bob_icp = bob.incept(None)?;
+    for witness in &witnesses {
+        witness.process_event(&icp)?;
+        witness.process_event(&bob_icp)?;
+    }
+    watcher.watch(alice.prefix());
+
+    // alice shows each witness different interaction event
+    let conflicting = alice.make_ixn_event(alice.prefix(), alice.payload_seals(Some("second")))?;
+    let ixn = alice.make_ixn(Some("first"))?;
+    let signature = alice
+        .key_manager()
+        .lock()
+        .unwrap()
+        .sign(&conflicting.serialize()?)?;
+    let conflicting = conflicting.sign(
+        vec![AttachedSignaturePrefix::new(
+            SelfSigning::Ed25519Sha512,
+            signature,
+            0,
+        )],
+        None,
+    );
+    let old_ksn = witnesses[1].get_ksn_for_prefix(alice.prefix())?;
+    witnesses[0].process_event(&ixn)?;
+    witnesses[1].process_event(&conflicting)?;
+
+    let kel = witnesses[0]
+        .processor
+        .get_kerl_with_receipts(alice.prefix())?
+        .unwrap();
+    assert!(watcher.process_kel(&witnesses[0].prefix, &kel)?.is_empty());
+    assert_eq!(
+        watcher.processor.compute_state(alice.prefix())?,
+        alice.get_state()?
+    );
+    let ksn = witnesses[0].get_ksn_for_prefix(alice.prefix())?;
+    assert!(watcher.process_ksn(&ksn)?.is_empty());
+    // KSN must be signed by alice's witness
+    let other_root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let other = Witness::new(other_root.path())?;
+    other.process_event(&icp)?;
+    other.process_event(&ixn)?;
+    let ksn = other.get_ksn_for_prefix(alice.prefix())?;
+    assert!(watcher.process_ksn(&ksn).is_err());
+
+    // second witness disagrees
+    let ksn = witnesses[1].get_ksn_for_prefix(alice.prefix())?;
+    assert_eq!(
+        watcher.process_ksn(&ksn)?,
+        vec![DuplicityAlert::ConflictingKeyState {
+            witness: witnesses[1].prefix.clone(),
+            prefix: alice.prefix().clone(),
+            sn: 1,
+            digest: conflicting.event_message.get_digest(),
+        }]
+    );
+    // replayed older KSN doesn't hide disagreement
+    assert!(watcher.process_ksn(&old_ksn)?.is_empty());
+    let ksn = witnesses[0].get_ksn_for_prefix(alice.prefix())?;
+    assert_eq!(
+        watcher.process_ksn(&ksn)?,
+        vec![DuplicityAlert::ConflictingKeyState {
+            witness: witnesses[0].prefix.clone(),
+            prefix: alice.prefix().clone(),
+            sn: 1,
+            digest: ixn.event_message.get_digest(),
+        }]
+    );
+    let kel = witnesses[1]
+        .processor
+        .get_kerl_with_receipts(alice.prefix())?
+        .unwrap();
+    // conflicting event must be receipted by witness which served it
+    assert!(watcher.process_kel(&witnesses[0].prefix, &kel)?.is_empty());
+    // and signed by alice
+    let mut forged = conflicting.clone();
+    forged.signatures = bob_icp.signatures.clone();
+    let forged_kel = [
+        SignedEventData::from(&forged).to_cesr()?,
+        SignedEventData::from(
+            witnesses[1]
+                .get_receipts(alice.prefix())
+                .into_iter()
+                .find(|rct| rct.body.event.sn == 1)
+                .unwrap(),
+        )
+        .to_cesr()?,
+    ]
+    .concat();
+    assert!(watcher
+        .process_kel(&witnesses[1].prefix, &forged_kel)?
+        .is_empty());
+    assert_eq!(
+        watcher.process_kel(&witnesses[1].prefix, &kel)?,
+        vec![DuplicityAlert::ConflictingEvent {
+            witness: witnesses[1].prefix.clone(),
+            event: Box::new(conflicting),
+        }]
+    );
+    // first-seen log is kept
+    assert_eq!(
+        watcher.processor.compute_state(alice.prefix())?,
+        alice.get_state()?
+    );
+
+    // not watched identifiers are skipped
+    let kel = witnesses[1].processor.get_kerl(bob.prefix())?.unwrap();
+    assert!(watcher.process_kel(&witnesses[1].prefix, &kel)?.is_empty());
+    assert!(watcher.processor.compute_state(bob.prefix())?.is_none());
+
+    Ok(())
+}
+
+#[cfg(feature = "query")]
+#[test]
+fn test_endpoint_replies() -> Result<(), Error> {
+    use crate::{
+        event_message::Digestible,
+        keri::witness::Witness,
+        oobi::{Role, Scheme},
+        prefix::IdentifierPrefix,
+        query::{QueryError, Route},
+        signer::CryptoBox,
+    };
+    use std::{thread::sleep, time::Duration};
+    use tempfile::Builder;
+    use url::Url;
+
+    let witness_root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let witness = Witness::new(witness_root.path())?;
+    let witness_id = IdentifierPrefix::Basic(witness.prefix.clone());
+
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db = Arc::new(SledEventDatabase::new(root.path()).unwrap());
+    let mut alice = Keri::new(db, Arc::new(Mutex::new(CryptoBox::new()?)))?;
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db = Arc::new(SledEventDatabase::new(root.path()).unwrap());
+    let mut bob = Keri::new(db, Arc::new(Mutex::new(CryptoBox::new()?)))?;
+
+    let icp = alice.incept(Some(vec![witness.prefix.clone()]))?;
+    witness.process_event(&icp)?;
+
+    // end role is accepted by controller and witness
+    let add = alice.add_end_role(Role::Witness, &witness_id)?;
+    assert_eq!(alice.db().get_end_roles(alice.prefix()).unwrap().count(), 1);
+    assert_eq!(witness.processor.process_signed_reply(&add)?, None);
+
+    // newer reply replaces accepted one, older is stale
+    sleep(Duration::from_millis(1));
+    let cut = alice.cut_end_role(Role::Witness, &witness_id)?;
+    witness.processor.process_signed_reply(&cut)?;
+    let accepted: Vec<_> = witness
+        .processor
+        .db
+        .get_end_roles(alice.prefix())
+        .unwrap()
+        .map(|rpy| rpy.reply.event.get_digest())
+        .collect();
+    assert_eq!(accepted, vec![cut.reply.event.get_digest()]);
+    assert!(matches!(
+        witness.processor.process_signed_reply(&add),
+        Err(Error::QueryError(QueryError::StaleRpy))
+    ));
+
+    // reply of unknown controller waits in escrow for its KEL
+    let bob_icp = bob.incept(None)?;
+    let bob_add = bob.add_end_role(Role::Watcher, &witness_id)?;
+    assert!(matches!(
+        witness.processor.process_signed_reply(&bob_add),
+        Err(Error::QueryError(QueryError::OutOfOrderEventError))
+    ));
+    assert!(witness.processor.db.get_end_roles(bob.prefix()).is_none());
+    witness.processor.process_event(&bob_icp)?;
+    witness.processor.process_escrow()?;
+    assert_eq!(
+        witness
+            .processor
+            .db
+            .get_end_roles(bob.prefix())
+            .unwrap()
+            .map(|rpy| rpy.reply.event.get_digest())
+            .collect::<Vec<_>>(),
+        vec![bob_add.reply.event.get_digest()]
+    );
+
+    // location schemes are updated per scheme
+    let url = Url::parse("http://127.0.0.1:5642/").unwrap();
+    let old_http = witness.make_loc_scheme(Scheme::Http, url.clone())?;
+    sleep(Duration::from_millis(1));
+    let http = witness.make_loc_scheme(Scheme::Http, url.clone())?;
+    let tcp = witness.make_loc_scheme(Scheme::Tcp, url)?;
+    alice.processor.process_signed_reply(&http)?;
+    alice.processor.process_signed_reply(&tcp)?;
+    assert!(matches!(
+        alice.processor.process_signed_reply(&old_http),
+        Err(Error::QueryError(QueryError::StaleRpy))
+    ));
+    assert_eq!(
+        alice
+            .db()
+            .get_loc_schemes(&witness_id)
+            .unwrap()
+            .map(|rpy| rpy.reply.event.get_digest())
+            .collect::<Vec<_>>(),
+        vec![http.reply.event.get_digest(), tcp.reply.event.get_digest()]
+    );
+
+    // reply data must concern the signer
+    let mut forged = alice.add_end_role(Role::Watcher, &witness_id)?;
+    forged.reply.event.content.route = Route::LocScheme;
+    assert!(witness.processor.process_signed_reply(&forged).is_err());
+
+    Ok(())
+}
+
+#[cfg(feature = "query")]
+#[test]
+fn test_reply_routes() -> Result<(), Error> {
+    use crate::{
+        derivation::self_addressing::SelfAddressing,
+        event::{EventMessage, SerializationFormats},
+        event_parsing::{message::signed_message, SignedEventData},
+        prefix::Prefix,
+        query::{
+            reply::{ReplyEvent, ReplyPayload},
+            QueryError, Route,
+        },
+        signer::CryptoBox,
+    };
+    use serde::{Deserialize, Serialize};
+    use tempfile::Builder;
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct Status {
+        status: String,
+        seq: u64,
+    }
+
+    let mut controllers = vec![];
+    let mut roots = vec![];
+    for _ in 0..3 {
+        let root = Builder::new().prefix("test-db").tempdir().unwrap();
+        let db = Arc::new(SledEventDatabase::new(root.path()).unwrap());
+        controllers.push(Keri::new(db, Arc::new(Mutex::new(CryptoBox::new()?)))?);
+        roots.push(root);
+    }
+    let (mut alice, mut bob, mut carol) = (
+        controllers.remove(0),
+        controllers.remove(0),
+        controllers.remove(0),
+    );
+    let alice_icp = alice.incept(None)?;
+    bob.incept(None)?;
+    let carol_icp = carol.incept(None)?;
+    bob.processor.process_event(&alice_icp)?;
+
+    let status = |seq| Status {
+        status: "online".into(),
+        seq,
+    };
+    let first = alice.make_reply("/app/status", &status(1))?;
+
+    // replies of routes without handler are rejected
+    assert!(bob.processor.process_signed_reply(&first).is_err());
+
+    let received = Arc::new(Mutex::new(vec![]));
+    let handled = Arc::clone(&received);
+    bob.register_reply_handler("/app/status", move |rpy, status: Status| {
+        handled
+            .lock()
+            .unwrap()
+            .push((rpy.signature.get_signer(), status));
+        Ok(())
+    })?;
+
+    // reply data keeps its serialization, so signature verifies after parsing
+    let stream = SignedEventData::from(first.clone()).to_cesr()?;
+    let parsed = Message::try_from(signed_message(&stream).unwrap().1)?;
+    bob.processor.process(parsed)?;
+    assert_eq!(
+        received.lock().unwrap().clone(),
+        vec![(alice.prefix().clone(), status(1))]
+    );
+
+    // BADA policy applies to application replies too
+    let second = alice.make_reply("/app/status", &status(2))?;
+    bob.processor.process_signed_reply(&second)?;
+    assert!(matches!(
+        bob.processor.process_signed_reply(&first),
+        Err(Error::QueryError(QueryError::StaleRpy))
+    ));
+    assert_eq!(received.lock().unwrap().len(), 2);
+
+    // replies of unknown signers wait in escrow
+    let carol_rpy = carol.make_reply("/app/status", &status(1))?;
+    assert!(matches!(
+        bob.processor.process_signed_reply(&carol_rpy),
+        Err(Error::QueryError(QueryError::OutOfOrderEventError))
+    ));
+    bob.processor.process_event(&carol_icp)?;
+    bob.processor.process_escrow()?;
+    assert_eq!(
+        received.lock().unwrap().last(),
+        Some(&(carol.prefix().clone(), status(1)))
+    );
+
+    // data not matching handler payload isn't accepted
+    let improper = alice.make_reply("/app/status", &"online")?;
+    assert!(bob.processor.process_signed_reply(&improper).is_err());
+    assert_eq!(received.lock().unwrap().len(), 3);
+
+    // application data shaped like KERI reply data is kept as is and
+    // reply is kept under its signer
+    bob.register_reply_handler("/app/role", |_, _: serde_json::Value| Ok(()))?;
+    let role = serde_json::json!({
+        "cid": carol.prefix().to_str(),
+        "role": "witness",
+        "eid": carol.prefix().to_str(),
+    });
+    let rpy = alice.make_reply("/app/role", &role)?;
+    let stream = SignedEventData::from(rpy).to_cesr()?;
+    let parsed = match Message::try_from(signed_message(&stream).unwrap().1)? {
+        Message::KeyStateNotice(rpy) => rpy,
+        _ => panic!("expected reply"),
+    };
+    assert_eq!(
+        parsed.reply.event.get_reply_data(),
+        ReplyPayload::Data(role.clone())
+    );
+    bob.processor.process_signed_reply(&parsed)?;
+    let route = Route::Custom("/app/role".into());
+    assert!(bob
+        .processor
+        .db
+        .get_accepted_replys(alice.prefix())
+        .unwrap()
+        .any(|rpy| rpy.reply.event.get_route() == route));
+    assert!(!bob
+        .processor
+        .db
+        .get_accepted_replys(carol.prefix())
+        .into_iter()
+        .flatten()
+        .any(|rpy| rpy.reply.event.get_route() == route));
+
+    // KERI routes are reserved
+    for reserved in ["/loc/scheme", "log", "/mbx", "/end/role/add", "/ksn/x", ""] {
+        assert!(bob
+            .register_reply_handler(reserved, |_, _: serde_json::Value| Ok(()))
+            .is_err());
+        assert!(alice.make_reply(reserved, &role).is_err());
+    }
+
+    // data not fitting KERI route doesn't deserialize
+    let rpy = ReplyEvent::new_reply(
+        ReplyPayload::Data(role),
+        Route::LocScheme,
+        SelfAddressing::Blake3_256,
+        SerializationFormats::JSON,
+    )?;
+    assert!(serde_json::from_slice::<EventMessage<ReplyEvent>>(&rpy.serialize()?).is_err());
+
+    Ok(())
+}
+
+#[cfg(feature = "query")]
+#[test]
+fn test_query_args() -> Result<(), Error> {
+    use crate::{
+        derivation::{self_addressing::SelfAddressing, self_signing::SelfSigning},
+        event::{
+            sections::seal::{DigestSeal, Seal},
+            SerializationFormats,
+        },
+        event_message::signed_event_message::Message,
+        event_parsing::message::signed_event_stream,
+        keri::witness::Witness,
+        prefix::{AttachedSignaturePrefix, IdentifierPrefix},
+        query::{
+            query::{QueryArgs, QueryEvent, SignedQuery},
+            ReplyType, Route,
+        },
+        signer::{CryptoBox, KeyManager},
+    };
+    use std::convert::TryFrom;
+    use tempfile::Builder;
+
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db = Arc::new(SledEventDatabase::new(root.path())?);
+    let key_manager = Arc::new(Mutex::new(CryptoBox::new()?));
+    let mut alice = Keri::new(db, Arc::clone(&key_manager))?;
+
+    let witness_root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let witness = Witness::new(witness_root.path())?;
+
+    let seal = Seal::Digest(DigestSeal {
+        dig: SelfAddressing::Blake3_256.derive(b"data"),
+    });
+    let icp = alice.incept(Some(vec![witness.prefix.clone()]))?;
+    witness.processor.process_event(&icp)?;
+    let anchoring = alice.anchor(vec![seal.clone()])?;
+    witness.processor.process_event(&anchoring)?;
+    let before_last = chrono::Local::now().into();
+    let last = alice.make_ixn(None)?;
+    witness.processor.process_event(&last)?;
+
+    let query = |args: QueryArgs| -> Result<Vec<u64>, Error> {
+        let qry = QueryEvent::new_query_with_args(
+            Route::Log,
+            args,
+            "",
+            SerializationFormats::JSON,
+            &SelfAddressing::Blake3_256,
+        )?;
+        let signature = AttachedSignaturePrefix::new(
+            SelfSigning::Ed25519Sha512,
+            key_manager.lock().unwrap().sign(&qry.serialize()?)?,
+            0,
+        );
+        let qry = SignedQuery::new(qry, alice.prefix().clone(), vec![signature]);
+        match witness.process_signed_query(qry)? {
+            ReplyType::Kel(kel) => Ok(signed_event_stream(&kel)
+                .unwrap()
+                .1
+                .into_iter()
+                .filter_map(|data| match Message::try_from(data) {
+                    Ok(Message::Event(ev)) => Some(ev.event_message.event.get_sn()),
+                    _ => None,
+                })
+                .collect()),
+            _ => Err(Error::SemanticError("Expected KEL".into())),
+        }
+    };
+
+    let args = QueryArgs::new(alice.prefix().clone());
+    assert_eq!(query(args.clone())?, vec![0, 1, 2]);
+    assert_eq!(query(args.clone().with_sn(1))?, vec![1, 2]);
+    assert_eq!(query(args.clone().with_timestamp(before_last))?, vec![2]);
+    assert_eq!(query(args.clone().with_anchor(seal))?, vec![1]);
+
+    // seal not anchored in kel
+    let unknown = Seal::Digest(DigestSeal {
+        dig: SelfAddressing::Blake3_256.derive(b"other data"),
+    });
+    assert!(query(args.clone().with_anchor(unknown)).is_err());
+
+    // query addressed to the witness or another one
+    let witness_id = IdentifierPrefix::Basic(witness.prefix.clone());
+    assert_eq!(query(args.clone().with_source(witness_id))?, vec![0, 1, 2]);
+    let other: IdentifierPrefix = "BoQy7bwiYr80qXoISsMdGvfXmCFpQ2ULO8grr2JaW-gU".parse()?;
+    assert!(matches!(
+        query(args.with_source(other)),
+        Err(Error::SemanticError(_))
+    ));
+
+    // arguments survive serialization
+    let args = QueryArgs::new(alice.prefix().clone())
+        .with_sn(1)
+        .with_timestamp(before_last);
+    let qry = QueryEvent::new_query_with_args(
+        Route::Log,
+        args.clone(),
+        "",
+        SerializationFormats::JSON,
+        &SelfAddressing::Blake3_256,
+    )?;
+    let parsed: QueryEvent = serde_json::from_slice(&qry.serialize()?).unwrap();
+    assert_eq!(parsed.get_query_data().data, args);
+
+    Ok(())
+}
+
+#[cfg(feature = "query")]
+#[test]
+fn test_controller_queries() -> Result<(), Error> {
+    use crate::{
+        event_message::signed_event_message::Message,
+        event_parsing::{message::signed_event_stream, SignedEventData},
+        keri::witness::Witness,
+        prefix::IdentifierPrefix,
+        query::{query::QueryArgs, ReplyType, Route},
+        signer::CryptoBox,
+    };
+    use std::convert::TryFrom;
+    use tempfile::Builder;
+
+    let witness_root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let witness = Witness::new(witness_root.path())?;
+
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db = Arc::new(SledEventDatabase::new(root.path())?);
+    let mut alice = Keri::new(db, Arc::new(Mutex::new(CryptoBox::new()?)))?;
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db = Arc::new(SledEventDatabase::new(root.path())?);
+    let mut bob = Keri::new(db, Arc::new(Mutex::new(CryptoBox::new()?)))?;
+
+    // witness receipts bob's events and knows alice's keys
+    let alice_icp = alice.incept(None)?;
+    witness.process_event(&alice_icp)?;
+    for event in [
+        bob.incept(Some(vec![witness.prefix.clone()]))?,
+        bob.rotate()?,
+        bob.make_ixn(None)?,
+    ] {
+        witness.process_event(&event)?;
+    }
+
+    // alice gets bob's kel from witness
+    let qry = alice.query_kel(&witness.prefix, bob.prefix())?;
+    alice.process_query_response(witness.process_signed_query(qry)?)?;
+    assert_eq!(alice.get_state_for_prefix(bob.prefix())?, bob.get_state()?);
+    // receipts are stored along with events
+    let kel = alice
+        .processor
+        .get_kerl_with_receipts(bob.prefix())?
+        .unwrap();
+    assert_eq!(
+        kel,
+        witness
+            .processor
+            .get_kerl_with_receipts(bob.prefix())?
+            .unwrap()
+    );
+    // known events are skipped
+    let qry = alice.query_kel(&witness.prefix, bob.prefix())?;
+    alice.process_query_response(witness.process_signed_query(qry)?)?;
+
+    // and bob's key state notice, sent as cesr stream
+    let qry = alice.query_ksn(&witness.prefix, bob.prefix())?;
+    let response = witness.respond(Message::Query(qry))?;
+    let rpy = match Message::try_from(signed_event_stream(&response).unwrap().1[0].clone())? {
+        Message::KeyStateNotice(rpy) => rpy,
+        _ => panic!("expected reply"),
+    };
+    assert_eq!(rpy.reply.event.get_state()?, bob.get_state()?.unwrap());
+    alice.process_query_response(ReplyType::Rep(rpy.clone()))?;
+    let accepted = alice
+        .db()
+        .get_accepted_replys(bob.prefix())
+        .unwrap()
+        .count();
+    assert_eq!(accepted, 1);
+
+    // queries are verified against signer's keys
+    let qry = alice.query_kel(&witness.prefix, bob.prefix())?;
+    let parsed = signed_event_stream(&SignedEventData::from(qry.clone()).to_cesr()?)
+        .unwrap()
+        .1[0]
+        .clone();
+    assert!(matches!(Message::try_from(parsed)?, Message::Query(_)));
+    assert!(bob.processor.process(Message::Query(qry.clone())).is_err());
+    alice.processor.process(Message::Query(qry.clone()))?;
+    let mut forged = qry;
+    forged.signer = bob.prefix().clone();
+    assert!(matches!(
+        witness.process_signed_query(forged),
+        Err(Error::SignatureVerificationError)
+    ));
+
+    // controller answers queries about KELs it knows
+    let qry = bob.sign_query(Route::Log, QueryArgs::new(alice.prefix().clone()))?;
+    let kel = alice.respond(&SignedEventData::from(qry).to_cesr()?)?;
+    bob.process_query_response(ReplyType::Kel(kel))?;
+    assert_eq!(
+        bob.get_state_for_prefix(alice.prefix())?,
+        alice.get_state()?
+    );
+    let qry = bob.sign_query(Route::Ksn, QueryArgs::new(alice.prefix().clone()))?;
+    let response = alice.respond(&SignedEventData::from(qry).to_cesr()?)?;
+    let rpy = match Message::try_from(signed_event_stream(&response).unwrap().1[0].clone())? {
+        Message::KeyStateNotice(rpy) => rpy,
+        _ => panic!("expected reply"),
+    };
+    assert_eq!(rpy.reply.event.get_state()?, alice.get_state()?.unwrap());
+    assert_eq!(rpy.signature.get_signer(), alice.prefix().clone());
+    // replies get no response
+    assert!(bob
+        .respond(&SignedEventData::from(rpy).to_cesr()?)?
+        .is_empty());
+    // nor do queries addressed to someone else
+    let qry = bob.sign_query(
+        Route::Log,
+        QueryArgs::new(alice.prefix().clone())
+            .with_source(IdentifierPrefix::Basic(witness.prefix.clone())),
+    )?;
+    assert!(alice
+        .respond(&SignedEventData::from(qry).to_cesr()?)?
+        .is_empty());
+
+    Ok(())
+}
+
+#[cfg(feature = "query")]
+#[test]
+fn test_mailbox() -> Result<(), Error> {
+    use crate::{
+        event_message::signed_event_message::Message,
+        event_parsing::{
+            message::{signed_event_stream, signed_message},
+            SignedEventData,
+        },
+        keri::witness::Witness,
+        query::{
+            mailbox::{MailboxResponse, DELEGATE_TOPIC, MULTISIG_TOPIC, RECEIPT_TOPIC},
+            query::QueryArgs,
+            reply::ReplyPayload,
+            ReplyType, Route,
+        },
+        signer::CryptoBox,
+    };
+    use std::{collections::BTreeMap, convert::TryFrom};
+    use tempfile::Builder;
+
+    let witness_root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let witness = Witness::new(witness_root.path())?;
+
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db = Arc::new(SledEventDatabase::new(root.path())?);
+    let mut alice = Keri::new(db, Arc::new(Mutex::new(CryptoBox::new()?)))?;
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db = Arc::new(SledEventDatabase::new(root.path())?);
+    let mut bob = Keri::new(db, Arc::new(Mutex::new(CryptoBox::new()?)))?;
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db = Arc::new(SledEventDatabase::new(root.path())?);
+    let dave = Keri::new(db, Arc::new(Mutex::new(CryptoBox::new()?)))?;
+
+    // witness serves alice, but not bob
+    let alice_icp = alice.incept(Some(vec![witness.prefix.clone()]))?;
+    witness.process_event(&alice_icp)?;
+    let bob_icp = bob.incept(None)?;
+    witness.process_event(&bob_icp)?;
+    alice.processor.process_event(&bob_icp)?;
+
+    // bob's receipt of alice's icp waits in her mailbox
+    bob.processor.process_event(&alice_icp)?;
+    let rct = bob.make_rct(alice_icp.event_message.clone())?;
+    witness.respond(Message::TransferableRct(Box::new(rct.clone())))?;
+    // receipt with signatures of other event isn't stored
+    let mut forged = rct;
+    forged.signatures = bob.make_rct(bob_icp.event_message.clone())?.signatures;
+    assert!(witness
+        .respond(Message::TransferableRct(Box::new(forged)))
+        .is_err());
+    // so does dave's delegation request
+    let request = dave.incept_delegated(alice.prefix())?;
+    let response = witness.respond(Message::Event(Box::new(request.clone())))?;
+    assert!(response.is_empty());
+    // unless it isn't signed by the delegate
+    let mut forged = request.clone();
+    forged.signatures = bob_icp.signatures.clone();
+    assert!(witness.respond(Message::Event(Box::new(forged))).is_err());
+    // and bob's exn message, under topic given by its route
+    let exn = bob.make_exchange(alice.prefix(), "/multisig/icp", &"proposal")?;
+    witness.respond(Message::Exchange(exn.clone()))?;
+    let to_bob = alice.make_exchange(bob.prefix(), "/multisig/icp", &"proposal")?;
+    assert!(witness.respond(Message::Exchange(to_bob)).is_err());
+
+    let cursors: BTreeMap<String, u64> = vec![
+        (RECEIPT_TOPIC.to_string(), 0),
+        (DELEGATE_TOPIC.to_string(), 0),
+    ]
+    .into_iter()
+    .collect();
+    let qry = alice.query_mailbox(&witness.prefix, cursors.clone())?;
+    let mbx = match witness.process_signed_query(qry)? {
+        ReplyType::Mbx(mbx) => mbx,
+        _ => panic!("expected mailbox response"),
+    };
+    // only requested topics are returned
+    assert_eq!(mbx.topics.len(), 2);
+    assert_eq!(mbx.topics[RECEIPT_TOPIC].len(), 1);
+    assert_eq!(mbx.cursors[RECEIPT_TOPIC], 1);
+    assert_eq!(mbx.cursors[DELEGATE_TOPIC], 1);
+
+    // alice can approve the request
+    let stream = &mbx.topics[DELEGATE_TOPIC][0];
+    let delegated = match Message::try_from(signed_event_stream(stream).unwrap().1[0].clone())? {
+        Message::Event(ev) => *ev,
+        _ => panic!("expected event"),
+    };
+    assert_eq!(delegated, request);
+    alice.approve_delegation(&delegated)?;
+
+    // receipts are processed into alice's database
+    alice.process_query_response(ReplyType::Mbx(mbx.clone()))?;
+    assert_eq!(
+        alice.db().get_receipts_t(alice.prefix()).unwrap().count(),
+        1
+    );
+
+    // next query with updated cursors gets only new messages
+    let mut cursors = mbx.cursors.clone();
+    cursors.insert(MULTISIG_TOPIC.to_string(), 0);
+    let qry = alice.query_mailbox(&witness.prefix, cursors)?;
+    let mbx = match witness.process_signed_query(qry.clone())? {
+        ReplyType::Mbx(mbx) => mbx,
+        _ => panic!("expected mailbox response"),
+    };
+    // sent as signed reply, keeping topics and cursors
+    let stream = witness.respond(Message::Query(qry))?;
+    let rpy = match Message::try_from(signed_message(&stream).unwrap().1)? {
+        Message::KeyStateNotice(rpy) => rpy,
+        _ => panic!("expected reply"),
+    };
+    assert_eq!(rpy.reply.event.get_route(), Route::ReplyMbx);
+    assert_eq!(
+        rpy.reply.event.get_reply_data(),
+        ReplyPayload::Mbx(mbx.clone())
+    );
+    assert_eq!(
+        mbx.topics[MULTISIG_TOPIC],
+        vec![SignedEventData::from(exn).to_cesr()?]
+    );
+    alice.process_query_response(ReplyType::Rep(rpy.clone()))?;
+    assert!(mbx.topics[RECEIPT_TOPIC].is_empty());
+    assert_eq!(mbx.cursors[RECEIPT_TOPIC], 1);
+    assert_eq!(mbx.cursors[MULTISIG_TOPIC], 1);
+
+    // mailbox reply must come from alice's witness
+    let forged = bob.sign_reply(rpy.reply.event.get_reply_data(), Route::ReplyMbx)?;
+    assert!(alice
+        .process_query_response(ReplyType::Rep(forged))
+        .is_err());
+    // and messages which fail to process aren't ignored
+    let mut forged = bob.make_rct(alice_icp.event_message.clone())?;
+    forged.signatures = bob.make_rct(bob_icp.event_message.clone())?.signatures;
+    let forged = MailboxResponse {
+        topics: vec![(
+            RECEIPT_TOPIC.to_string(),
+            vec![SignedEventData::from(forged).to_cesr()?],
+        )]
+        .into_iter()
+        .collect(),
+        cursors: vec![(RECEIPT_TOPIC.to_string(), 1)].into_iter().collect(),
+    };
+    assert!(alice
+        .process_query_response(ReplyType::Mbx(forged))
+        .is_err());
+
+    // witness passes exn to its own handler, if it registered one, and
+    // still keeps it for the recipient
+    let handled = Arc::new(Mutex::new(0));
+    let counter = Arc::clone(&handled);
+    witness
+        .processor
+        .register_exchange_handler("/multisig/icp", move |_, _: String| {
+            *counter.lock().unwrap() += 1;
+            Ok(())
+        })?;
+    let exn = bob.make_exchange(alice.prefix(), "/multisig/icp", &"proposal")?;
+    witness.respond(Message::Exchange(exn.clone()))?;
+    assert_eq!(*handled.lock().unwrap(), 1);
+    let qry = alice.query_mailbox(&witness.prefix, mbx.cursors.clone())?;
+    match witness.process_signed_query(qry)? {
+        ReplyType::Mbx(mbx) => assert_eq!(
+            mbx.topics[MULTISIG_TOPIC],
+            vec![SignedEventData::from(exn).to_cesr()?]
+        ),
+        _ => panic!("expected mailbox response"),
+    };
+
+    // mailbox can be read only by its owner
+    let qry = bob.sign_query(
+        Route::Mbx,
+        QueryArgs::new(alice.prefix().clone()).with_topics(mbx.cursors),
+    )?;
+    assert!(witness.process_signed_query(qry).is_err());
+
+    Ok(())
+}
+
+#[cfg(feature = "query")]
+#[test]
+fn test_exchange() -> Result<(), Error> {
+    use crate::{
+        event_parsing::{message::signed_message, EventType, SignedEventData},
+        prefix::IdentifierPrefix,
+        signer::CryptoBox,
+    };
+    use serde::{Deserialize, Serialize};
+    use tempfile::Builder;
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct GroupProposal {
+        aids: Vec<IdentifierPrefix>,
+    }
+
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db = Arc::new(SledEventDatabase::new(root.path())?);
+    let mut alice = Keri::new(db, Arc::new(Mutex::new(CryptoBox::new()?)))?;
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db = Arc::new(SledEventDatabase::new(root.path())?);
+    let mut bob = Keri::new(db, Arc::new(Mutex::new(CryptoBox::new()?)))?;
+    let alice_icp = alice.incept(None)?;
+    bob.incept(None)?;
+    bob.processor.process_event(&alice_icp)?;
+
+    let proposal = GroupProposal {
+        aids: vec![alice.prefix().clone(), bob.prefix().clone()],
+    };
+    let exn = alice.make_exchange(bob.prefix(), "/multisig/icp", &proposal)?;
+
+    // messages of routes without handler are rejected
+    assert!(bob.processor.process_signed_exchange(&exn).is_err());
+
+    let received = Arc::new(Mutex::new(vec![]));
+    let handled = Arc::clone(&received);
+    bob.register_exchange_handler("/multisig/icp", move |exn, proposal: GroupProposal| {
+        handled.lock().unwrap().push((exn.get_signer(), proposal));
+        Ok(())
+    })?;
+
+    // signatures are attached as transferable signatures group
+    let stream = SignedEventData::from(exn.clone()).to_cesr()?;
+    assert!(String::from_utf8(stream.clone()).unwrap().contains("-FAB"));
+    let parsed = signed_message(&stream).unwrap().1;
+    assert!(matches!(parsed.deserialized_event, EventType::Exn(_)));
+    let parsed = Message::try_from(parsed)?;
+    assert!(matches!(&parsed, Message::Exchange(parsed) if parsed.signer_seal == exn.signer_seal));
+    bob.processor.process(parsed)?;
+    assert_eq!(
+        received.lock().unwrap().clone(),
+        vec![(alice.prefix().clone(), proposal.clone())]
+    );
+
+    // signer must match signatures
+    let mut forged = exn.clone();
+    forged.signer_seal = bob
+        .processor
+        .get_last_establishment_event_seal(bob.prefix())?
+        .unwrap();
+    assert!(matches!(
+        bob.processor.process_signed_exchange(&forged),
+        Err(Error::SignatureVerificationError)
+    ));
+    // and signer's kel must be known
+    let carol_root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db = Arc::new(SledEventDatabase::new(carol_root.path())?);
+    let carol = Keri::new(db, Arc::new(Mutex::new(CryptoBox::new()?)))?;
+    carol.register_exchange_handler("/multisig/icp", |_, _: GroupProposal| Ok(()))?;
+    assert!(carol.processor.process_signed_exchange(&exn).is_err());
+
+    // message signed with rotated out keys is rejected
+    let rot = alice.rotate()?;
+    bob.processor.process_event(&rot)?;
+    assert!(bob.processor.process_signed_exchange(&exn).is_err());
+    let exn = alice.make_exchange(bob.prefix(), "/multisig/icp", &proposal)?;
+    bob.processor.process_signed_exchange(&exn)?;
+    assert_eq!(received.lock().unwrap().len(), 2);
+
+    Ok(())
+}
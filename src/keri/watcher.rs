@@ -0,0 +1,247 @@
+use std::{convert::TryFrom, path::Path, sync::Arc};
+
+use chrono::{DateTime, FixedOffset};
+
+use crate::{
+    database::sled::SledEventDatabase,
+    error::Error,
+    event_message::{
+        signature::Signature,
+        signed_event_message::{Message, SignedEventMessage, SignedNontransferableReceipt},
+    },
+    event_parsing::message::signed_event_stream,
+    prefix::{BasicPrefix, IdentifierPrefix, SelfAddressingPrefix},
+    processor::EventProcessor,
+    query::reply::SignedReply,
+    state::{EventSemantics, IdentifierState},
+};
+
+/// Duplicity Alert
+///
+/// Inconsistency found between views of watched identifier reported by
+/// witnesses and the first-seen log of the watcher.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DuplicityAlert {
+    /// Witness KEL contains event conflicting with first-seen one.
+    ConflictingEvent {
+        witness: BasicPrefix,
+        event: Box<SignedEventMessage>,
+    },
+    /// Witness KSN reports event digest at `sn` different from first-seen
+    /// one or from reported by other witness.
+    ConflictingKeyState {
+        witness: BasicPrefix,
+        prefix: IdentifierPrefix,
+        sn: u64,
+        digest: SelfAddressingPrefix,
+    },
+}
+
+/// Watcher
+///
+/// Monitors KELs of watched identifiers by ingesting KELs and KSNs served
+/// by their witnesses. First-seen version of every event is kept in own
+/// log, so disagreeing witnesses are detected.
+pub struct Watcher {
+    pub processor: EventProcessor,
+    watched: Vec<IdentifierPrefix>,
+    /// Last key state of every watched identifier reported by its
+    /// witnesses, with timestamp of the reporting KSN.
+    reported: Vec<(BasicPrefix, DateTime<FixedOffset>, IdentifierState)>,
+}
+
+impl Watcher {
+    pub fn new(path: &Path) -> Result<Self, Error> {
+        let db = Arc::new(SledEventDatabase::new(path)?);
+        Ok(Self {
+            processor: EventProcessor::new(db),
+            watched: vec![],
+            reported: vec![],
+        })
+    }
+
+    pub fn watch(&mut self, prefix: &IdentifierPrefix) {
+        if !self.watched.contains(prefix) {
+            self.watched.push(prefix.clone());
+        }
+    }
+
+    pub fn watched(&self) -> &[IdentifierPrefix] {
+        &self.watched
+    }
+
+    /// Ingests KEL (with receipts) of watched identifiers served by
+    /// `witness`. Events not seen before are added to first-seen log,
+    /// events conflicting with it are stored as duplicitous and reported,
+    /// if they're signed with keys valid at their sn and receipted by
+    /// `witness` within the KEL. Messages of not watched identifiers and
+    /// invalid ones are skipped.
+    ///
+    pub fn process_kel(
+        &self,
+        witness: &BasicPrefix,
+        kel: &[u8],
+    ) -> Result<Vec<DuplicityAlert>, Error> {
+        let messages = signed_event_stream(kel)
+            .map_err(|e| Error::DeserializeError(e.to_string()))?
+            .1
+            .into_iter()
+            .map(Message::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        let receipts: Vec<_> = messages
+            .iter()
+            .filter_map(|message| match message {
+                Message::NontransferableRct(rct) => Some(rct.clone()),
+                _ => None,
+            })
+            .collect();
+        let mut alerts = vec![];
+        for message in messages {
+            let prefix = match &message {
+                Message::Event(event) => event.event_message.event.get_prefix(),
+                Message::NontransferableRct(rct) => rct.body.event.prefix.clone(),
+                Message::TransferableRct(rct) => rct.body.event.prefix.clone(),
+                _ => continue,
+            };
+            if !self.watched.contains(&prefix) {
+                continue;
+            }
+            if let Message::Event(event) = &message {
+                if let Some(first_seen) = self
+                    .processor
+                    .get_event_at_sn(&prefix, event.event_message.event.get_sn())?
+                {
+                    if first_seen.signed_event_message.event_message != event.event_message
+                        && self.verify_conflicting_event(witness, event, &receipts)?
+                    {
+                        self.processor
+                            .db
+                            .add_duplicious_event(*event.clone(), &prefix)?;
+                        alerts.push(DuplicityAlert::ConflictingEvent {
+                            witness: witness.clone(),
+                            event: event.clone(),
+                        });
+                    }
+                    continue;
+                }
+            }
+            // invalid messages don't affect first-seen log
+            let _ = self.processor.process(message);
+        }
+        Ok(alerts)
+    }
+
+    /// Checks that `event` conflicting with first-seen log is signed with
+    /// keys valid at its sn and receipted by `witness`, designated for it,
+    /// in one of `receipts`.
+    fn verify_conflicting_event(
+        &self,
+        witness: &BasicPrefix,
+        event: &SignedEventMessage,
+        receipts: &[SignedNontransferableReceipt],
+    ) -> Result<bool, Error> {
+        let (prefix, sn) = (
+            event.event_message.event.get_prefix(),
+            event.event_message.event.get_sn(),
+        );
+        let previous = match sn {
+            0 => Some(IdentifierState::default()),
+            _ => self.processor.compute_state_at_sn(&prefix, sn - 1)?,
+        };
+        let state = match previous.map(|state| event.event_message.apply_to(state)) {
+            Some(Ok(state)) => state,
+            _ => return Ok(false),
+        };
+        let serialized = event.event_message.serialize()?;
+        if !state
+            .current
+            .verify(&serialized, &event.signatures)
+            .unwrap_or(false)
+            || !state.witnesses.contains(witness)
+        {
+            return Ok(false);
+        }
+        for rct in receipts {
+            if rct.body.event.prefix != prefix
+                || rct.body.event.sn != sn
+                || !event
+                    .event_message
+                    .check_digest(&rct.body.event.receipted_event_digest)?
+            {
+                continue;
+            }
+            for (signer, signature) in &rct.couplets {
+                if signer == witness && witness.verify(&serialized, signature)? {
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    /// Checks KSN reply signed by witness of watched identifier against
+    /// first-seen log and key states reported by other witnesses. KSN ahead
+    /// of first-seen log means the witness KEL should be processed. Signer
+    /// must be current witness of the identifier in first-seen log. KSN
+    /// older than the last one of the same witness is ignored, so replaying
+    /// it can't change reported key state.
+    ///
+    pub fn process_ksn(&mut self, rpy: &SignedReply) -> Result<Vec<DuplicityAlert>, Error> {
+        let witness = match &rpy.signature {
+            Signature::NonTransferable(witness, _) => witness.clone(),
+            Signature::Transferable(_, _) => {
+                return Err(Error::SemanticError("KSN not signed by witness".into()))
+            }
+        };
+        self.processor
+            .verify(&rpy.reply.serialize()?, &rpy.signature)?;
+        rpy.reply.check_digest()?;
+        let timestamp = rpy.reply.event.get_timestamp();
+        let state = rpy.reply.event.get_state()?;
+        if !self.watched.contains(&state.prefix) {
+            return Ok(vec![]);
+        }
+        let designated = self
+            .processor
+            .compute_state(&state.prefix)?
+            .is_some_and(|current| current.witnesses.contains(&witness));
+        if !designated {
+            return Err(Error::SemanticError(
+                "KSN not signed by witness of identifier".into(),
+            ));
+        }
+        if self.reported.iter().any(|(other, other_dt, other_state)| {
+            other == &witness && other_state.prefix == state.prefix && other_dt > &timestamp
+        }) {
+            return Ok(vec![]);
+        }
+
+        let first_seen_conflict = match self.processor.get_event_at_sn(&state.prefix, state.sn)? {
+            Some(first_seen) => !first_seen
+                .signed_event_message
+                .event_message
+                .check_digest(&state.last_event_digest)?,
+            None => false,
+        };
+        let reported_conflict = self.reported.iter().any(|(other, _, other_state)| {
+            other != &witness
+                && other_state.prefix == state.prefix
+                && other_state.sn == state.sn
+                && other_state.last_event_digest != state.last_event_digest
+        });
+        let mut alerts = vec![];
+        if first_seen_conflict || reported_conflict {
+            alerts.push(DuplicityAlert::ConflictingKeyState {
+                witness: witness.clone(),
+                prefix: state.prefix.clone(),
+                sn: state.sn,
+                digest: state.last_event_digest.clone(),
+            });
+        }
+        self.reported.retain(|(other, _, other_state)| {
+            other != &witness || other_state.prefix != state.prefix
+        });
+        self.reported.push((witness, timestamp, state));
+        Ok(alerts)
+    }
+}
@@ -1,9 +1,13 @@
+use std::collections::BTreeMap;
 use std::path::Path;
 use std::sync::Arc;
 
-use crate::query::reply::{ReplyEvent, SignedReply};
+use url::Url;
+
+use crate::query::reply::{ReplyEvent, ReplyPayload, SignedReply};
 use crate::query::{
     key_state_notice::KeyStateNotice,
+    mailbox::{exchange_topic, MailboxMessage, MailboxResponse, DELEGATE_TOPIC, RECEIPT_TOPIC},
     query::{QueryData, SignedQuery},
     ReplyType, Route,
 };
@@ -12,12 +16,21 @@ use crate::{
     database::sled::SledEventDatabase,
     derivation::{basic::Basic, self_addressing::SelfAddressing, self_signing::SelfSigning},
     error::Error,
-    event::SerializationFormats,
+    event::{event_data::EventData, receipt::Receipt, SerializationFormats},
+    event_message::signed_event_message::{
+        Message, SignedEventMessage, SignedNontransferableReceipt,
+    },
+    event_parsing::SignedEventData,
+    oobi::{LocationScheme, Oobi, Role, Scheme},
     prefix::{BasicPrefix, IdentifierPrefix},
     processor::EventProcessor,
     signer::{CryptoBox, KeyManager},
 };
 
+/// Witness
+///
+/// Non-transferable identifier receipting key events of controllers, which
+/// designated it as a witness, and answering queries about their key state.
 pub struct Witness {
     pub prefix: BasicPrefix,
     signer: CryptoBox,
@@ -31,7 +44,7 @@ impl Witness {
             let witness_db = Arc::new(SledEventDatabase::new(path).unwrap());
             EventProcessor::new(witness_db.clone())
         };
-        let prefix = Basic::Ed25519.derive(signer.public_key()?);
+        let prefix = Basic::Ed25519NT.derive(signer.public_key()?);
         Ok(Self {
             prefix,
             signer,
@@ -39,11 +52,310 @@ impl Witness {
         })
     }
 
+    /// Processes key event and, if resulting state designates us as a
+    /// witness, logs it first seen and makes and stores our receipt of it.
+    /// Receipt should be returned to the controller. Returns `None` for
+    /// events we don't witness, which wait in escrow for agreement of their
+    /// witnesses. Event we already logged, e.g. resent after lost
+    /// response, is receipted again.
+    ///
+    pub fn process_event(
+        &self,
+        event: &SignedEventMessage,
+    ) -> Result<Option<SignedNontransferableReceipt>, Error> {
+        let (id, sn) = (
+            event.event_message.event.get_prefix(),
+            event.event_message.event.get_sn(),
+        );
+        if let Some(logged) = self.processor.get_event_at_sn(&id, sn)? {
+            if logged.signed_event_message.event_message == event.event_message {
+                let designated = self
+                    .processor
+                    .compute_state_at_sn(&id, sn)?
+                    .is_some_and(|state| state.witnesses.contains(&self.prefix));
+                return if designated {
+                    self.make_receipt(event).map(Some)
+                } else {
+                    Ok(None)
+                };
+            }
+        }
+        let state = self.processor.apply_to_state(&event.event_message)?;
+        if !state.witnesses.contains(&self.prefix) {
+            return match self.processor.process_event(event) {
+                Ok(_) | Err(Error::NotEnoughReceiptsError) => Ok(None),
+                Err(e) => Err(e),
+            };
+        }
+        self.processor.process_own_event(event)?;
+        let rct = self.make_receipt(event)?;
+        self.processor.process_witness_receipt(rct.clone())?;
+        Ok(Some(rct))
+    }
+
+    /// Processes single message of any kind and returns CESR stream to be
+    /// sent back: receipt of witnessed event, reply or KEL answering query.
+    /// Mailbox messages are sent as `/mbx` reply.
+    /// Receipts are taken as distributed by the controller. Delegation
+    /// requests and validator receipts for identifiers we serve are also
+    /// stored in their mailboxes, as are `exn` messages addressed to them,
+    /// under topic given by their route. The latter are passed to our
+    /// handler only if we registered one for their route.
+    ///
+    pub fn respond(&self, message: Message) -> Result<Vec<u8>, Error> {
+        match message {
+            Message::Event(event) => match self.get_delegation_request_recipient(&event)? {
+                Some(delegator) if self.serves(&delegator)? => {
+                    // not approved yet, so it can't be processed
+                    self.verify_delegation_request(&event)?;
+                    self.store_mail(&delegator, DELEGATE_TOPIC, &SignedEventData::from(&*event))?;
+                    Ok(vec![])
+                }
+                _ => match self.process_event(&event)? {
+                    Some(rct) => SignedEventData::from(rct).to_cesr(),
+                    None => Ok(vec![]),
+                },
+            },
+            Message::NontransferableRct(rct) => {
+                self.process_receipts(&[rct])?;
+                Ok(vec![])
+            }
+            Message::TransferableRct(rct) => {
+                let recipient = rct.body.event.prefix.clone();
+                let mail = SignedEventData::from((*rct).clone());
+                // only valid receipts reach the mailbox
+                self.processor.process_validator_receipt(*rct)?;
+                if self.serves(&recipient)? {
+                    self.store_mail(&recipient, RECEIPT_TOPIC, &mail)?;
+                }
+                Ok(vec![])
+            }
+            Message::KeyStateNotice(rpy) => {
+                self.processor.process_signed_reply(&rpy)?;
+                Ok(vec![])
+            }
+            Message::Exchange(exn) => {
+                self.processor.verify_signed_exchange(&exn)?;
+                let route = exn.exchange.event.get_route();
+                // we handle it too, if we registered handler of its route
+                let handled = self.processor.has_exchange_handler(&route)?;
+                if handled {
+                    self.processor.process_signed_exchange(&exn)?;
+                }
+                match (exn.exchange.event.get_recipient(), route) {
+                    (Some(recipient), Route::Custom(route)) => {
+                        self.store_mail(&recipient, &exchange_topic(&route), &exn.into())?
+                    }
+                    _ if handled => (),
+                    _ => return Err(Error::SemanticError("Missing recipient".into())),
+                };
+                Ok(vec![])
+            }
+            Message::Query(qry) => match self.process_signed_query(qry)? {
+                ReplyType::Rep(rpy) => SignedEventData::from(rpy).to_cesr(),
+                ReplyType::Kel(kel) => Ok(kel),
+                ReplyType::Mbx(mbx) => {
+                    SignedEventData::from(self.make_mailbox_reply(mbx)?).to_cesr()
+                }
+            },
+        }
+    }
+
+    /// Stores receipts of other witnesses, distributed by the controller
+    /// after collecting them, so we can serve fully witnessed KEL. Every
+    /// couplet must be made by witness designated for receipted event.
+    /// Couplets of witnesses already known to us are skipped. Whole batch
+    /// is validated first, so nothing is stored if any receipt is invalid.
+    ///
+    pub fn process_receipts(&self, receipts: &[SignedNontransferableReceipt]) -> Result<(), Error> {
+        let validated = receipts
+            .iter()
+            .map(|rct| self.validate_receipt(rct))
+            .collect::<Result<Vec<_>, _>>()?;
+        for rct in validated.into_iter().flatten() {
+            self.processor.process_witness_receipt(rct)?;
+        }
+        Ok(())
+    }
+
+    /// Checks receipt of other witness and returns its couplets we don't
+    /// know yet, if any.
+    fn validate_receipt(
+        &self,
+        rct: &SignedNontransferableReceipt,
+    ) -> Result<Option<SignedNontransferableReceipt>, Error> {
+        let (id, sn) = (&rct.body.event.prefix, rct.body.event.sn);
+        let state = self
+            .processor
+            .compute_state_at_sn(id, sn)?
+            .filter(|state| state.sn == sn)
+            .ok_or_else(|| Error::SemanticError("Receipted event not found".into()))?;
+        if state.last_event_digest != rct.body.event.receipted_event_digest {
+            return Err(Error::IncorrectDigest);
+        }
+        if rct
+            .couplets
+            .iter()
+            .any(|(witness, _)| !state.witnesses.contains(witness))
+        {
+            return Err(Error::SemanticError(
+                "Receipt not made by designated witness".into(),
+            ));
+        }
+        let event = self
+            .processor
+            .get_event_at_sn(id, sn)?
+            .ok_or_else(|| Error::SemanticError("Receipted event not found".into()))?
+            .signed_event_message
+            .event_message
+            .serialize()?;
+        for (witness, signature) in &rct.couplets {
+            if !witness.verify(&event, signature)? {
+                return Err(Error::SignatureVerificationError);
+            }
+        }
+        let known: Vec<BasicPrefix> = self
+            .get_receipts(id)
+            .into_iter()
+            .filter(|known| known.body.event.sn == sn)
+            .flat_map(|known| known.couplets)
+            .map(|(witness, _)| witness)
+            .collect();
+        let couplets: Vec<_> = rct
+            .couplets
+            .iter()
+            .filter(|(witness, _)| !known.contains(witness))
+            .cloned()
+            .collect();
+        Ok((!couplets.is_empty()).then(|| SignedNontransferableReceipt::new(&rct.body, couplets)))
+    }
+
+    /// Stores `message` under `topic` in mailbox of `recipient`, until it
+    /// fetches it with `mbx` query. Only identifiers which designated us as
+    /// their witness have mailboxes.
+    ///
+    pub fn store_mail(
+        &self,
+        recipient: &IdentifierPrefix,
+        topic: &str,
+        message: &SignedEventData,
+    ) -> Result<(), Error> {
+        if !self.serves(recipient)? {
+            return Err(Error::SemanticError(
+                "Recipient not served by witness".into(),
+            ));
+        }
+        let message = MailboxMessage {
+            topic: topic.into(),
+            message: message.to_cesr()?,
+        };
+        self.processor.db.add_mailbox_message(message, recipient)
+    }
+
+    /// Returns messages from mailbox of `recipient`, starting from cursor
+    /// of every topic in `cursors`.
+    pub fn get_mail(
+        &self,
+        recipient: &IdentifierPrefix,
+        cursors: &BTreeMap<String, u64>,
+    ) -> MailboxResponse {
+        let mut response = MailboxResponse {
+            topics: cursors
+                .keys()
+                .map(|topic| (topic.clone(), vec![]))
+                .collect(),
+            cursors: cursors.clone(),
+        };
+        // index of the next message of every topic
+        let mut indexes: BTreeMap<String, u64> = BTreeMap::new();
+        let messages = self.processor.db.get_mailbox_messages(recipient);
+        for mail in messages.into_iter().flatten() {
+            if let Some(cursor) = cursors.get(&mail.topic) {
+                let idx = indexes.entry(mail.topic.clone()).or_default();
+                if *idx >= *cursor {
+                    response
+                        .topics
+                        .entry(mail.topic.clone())
+                        .or_default()
+                        .push(mail.message);
+                    response.cursors.insert(mail.topic, *idx + 1);
+                }
+                *idx += 1;
+            }
+        }
+        response
+    }
+
+    fn serves(&self, id: &IdentifierPrefix) -> Result<bool, Error> {
+        Ok(self
+            .processor
+            .compute_state(id)?
+            .is_some_and(|state| state.witnesses.contains(&self.prefix)))
+    }
+
+    /// Returns delegator which should approve `event`, if it's delegated
+    /// event without source seal.
+    fn get_delegation_request_recipient(
+        &self,
+        event: &SignedEventMessage,
+    ) -> Result<Option<IdentifierPrefix>, Error> {
+        if event.delegator_seal.is_some() {
+            return Ok(None);
+        }
+        Ok(match event.event_message.event.get_event_data() {
+            EventData::Dip(dip) => Some(dip.delegator),
+            EventData::Drt(_) => self
+                .processor
+                .compute_state(&event.event_message.event.get_prefix())?
+                .and_then(|state| state.delegator),
+            _ => None,
+        })
+    }
+
+    /// Checks signatures of delegation request, which can't be processed
+    /// before approval: inception against its own keys, rotation against
+    /// keys it rotates to, as committed by the delegate's current state.
+    fn verify_delegation_request(&self, event: &SignedEventMessage) -> Result<(), Error> {
+        let state = self.processor.apply_to_state(&event.event_message)?;
+        state
+            .current
+            .verify(&event.event_message.serialize()?, &event.signatures)?
+            .then_some(())
+            .ok_or(Error::SignatureVerificationError)
+    }
+
+    /// Receipts stored for events of `prefix`.
+    pub fn get_receipts(&self, prefix: &IdentifierPrefix) -> Vec<SignedNontransferableReceipt> {
+        self.processor
+            .db
+            .get_receipts_nt(prefix)
+            .map(|rcts| rcts.collect())
+            .unwrap_or_default()
+    }
+
+    fn make_receipt(
+        &self,
+        event: &SignedEventMessage,
+    ) -> Result<SignedNontransferableReceipt, Error> {
+        let message = &event.event_message;
+        let rcp = Receipt {
+            prefix: message.event.get_prefix(),
+            sn: message.event.get_sn(),
+            receipted_event_digest: message.get_digest(),
+        }
+        .to_message(SerializationFormats::JSON)?;
+        let signature = SelfSigning::Ed25519Sha512.derive(self.signer.sign(&message.serialize()?)?);
+        Ok(SignedNontransferableReceipt::new(
+            &rcp,
+            vec![(self.prefix.clone(), signature)],
+        ))
+    }
+
     pub fn get_ksn_for_prefix(&self, prefix: &IdentifierPrefix) -> Result<SignedReply, Error> {
         let state = self.processor.compute_state(prefix).unwrap().unwrap();
         let ksn = KeyStateNotice::new_ksn(state, SerializationFormats::JSON);
         let rpy = ReplyEvent::new_reply(
-            ksn,
+            ReplyPayload::Ksn(Box::new(ksn)),
             Route::ReplyKsn(IdentifierPrefix::Basic(self.prefix.clone())),
             SelfAddressing::Blake3_256,
             SerializationFormats::JSON,
@@ -58,33 +370,75 @@ impl Witness {
         ))
     }
 
+    /// Makes `/mbx` reply carrying mailbox messages, with their topics and
+    /// indexes, to be sent to their recipient.
+    fn make_mailbox_reply(&self, mbx: MailboxResponse) -> Result<SignedReply, Error> {
+        let rpy = ReplyEvent::new_reply(
+            ReplyPayload::Mbx(mbx),
+            Route::ReplyMbx,
+            SelfAddressing::Blake3_256,
+            SerializationFormats::JSON,
+        )?;
+        let signature = SelfSigning::Ed25519Sha512.derive(self.signer.sign(&rpy.serialize()?)?);
+        Ok(SignedReply::new_nontrans(
+            rpy,
+            self.prefix.clone(),
+            signature,
+        ))
+    }
+
+    /// Returns OOBI introducing this witness at `url`.
+    pub fn oobi(&self, url: Url) -> Oobi {
+        Oobi::new(
+            url,
+            IdentifierPrefix::Basic(self.prefix.clone()),
+            Role::Controller,
+            None,
+        )
+    }
+
+    /// Makes `/loc/scheme` reply announcing that this witness is reachable
+    /// at `url`. Reply is accepted in own database and should be served
+    /// along with witness OOBI.
+    pub fn make_loc_scheme(&self, scheme: Scheme, url: Url) -> Result<SignedReply, Error> {
+        let loc_scheme =
+            LocationScheme::new(IdentifierPrefix::Basic(self.prefix.clone()), scheme, url);
+        let rpy = ReplyEvent::new_reply(
+            ReplyPayload::LocScheme(loc_scheme),
+            Route::LocScheme,
+            SelfAddressing::Blake3_256,
+            SerializationFormats::JSON,
+        )?;
+        let signature = SelfSigning::Ed25519Sha512.derive(self.signer.sign(&rpy.serialize()?)?);
+        let rpy = SignedReply::new_nontrans(rpy, self.prefix.clone(), signature);
+        self.processor.process_signed_reply(&rpy)?;
+        Ok(rpy)
+    }
+
     pub fn process_signed_query(&self, qr: SignedQuery) -> Result<ReplyType, Error> {
-        let signatures = qr.signatures;
-        // check signatures
-        let kc = self
-            .processor
-            .compute_state(&qr.signer)?
-            .ok_or(Error::SemanticError("No identifier in db".into()))?
-            .current;
-
-        if kc.verify(&qr.envelope.serialize().unwrap(), &signatures)? {
-            // TODO check timestamps
-            // unpack and check what's inside
-            let route = qr.envelope.event.get_route();
-            self.process_query(route, qr.envelope.event.get_query_data())
-        } else {
-            Err(Error::SignatureVerificationError)
+        self.processor.verify_signed_query(&qr)?;
+        // TODO check timestamps
+        let route = qr.envelope.event.get_route();
+        let data = qr.envelope.event.get_query_data();
+        if route == Route::Mbx && data.data.i != qr.signer {
+            return Err(Error::SemanticError(
+                "Mailbox can be read only by its owner".into(),
+            ));
         }
+        self.process_query(route, data)
     }
 
     #[cfg(feature = "query")]
     fn process_query(&self, route: Route, qr: QueryData) -> Result<ReplyType, Error> {
-        match route {
-            Route::Log => {
-                Ok(ReplyType::Kel(self.processor.get_kerl(&qr.data.i)?.ok_or(
-                    Error::SemanticError("No identifier in db".into()),
-                )?))
+        if let Some(src) = &qr.data.src {
+            if src != &IdentifierPrefix::Basic(self.prefix.clone()) {
+                return Err(Error::SemanticError(
+                    "Query addressed to other witness".into(),
+                ));
             }
+        }
+        match route {
+            Route::Log => Ok(ReplyType::Kel(self.processor.get_queried_kel(&qr.data)?)),
             Route::Ksn => {
                 let i = qr.data.i;
                 // return reply message with ksn inside
@@ -95,7 +449,7 @@ impl Witness {
                     .ok_or(Error::SemanticError("No id in database".into()))?;
                 let ksn = KeyStateNotice::new_ksn(state, SerializationFormats::JSON);
                 let rpy = ReplyEvent::new_reply(
-                    ksn,
+                    ReplyPayload::Ksn(Box::new(ksn)),
                     Route::ReplyKsn(IdentifierPrefix::Basic(self.prefix.clone())),
                     SelfAddressing::Blake3_256,
                     SerializationFormats::JSON,
@@ -108,7 +462,13 @@ impl Witness {
                 );
                 Ok(ReplyType::Rep(rpy))
             }
-            _ => todo!(),
+            Route::Mbx => {
+                let args = qr.data;
+                Ok(ReplyType::Mbx(
+                    self.get_mail(&args.i, &args.topics.unwrap_or_default()),
+                ))
+            }
+            _ => Err(Error::SemanticError("Unsupported query route".into())),
         }
     }
 }
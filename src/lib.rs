@@ -11,5 +11,9 @@ pub mod processor;
 pub mod signer;
 pub mod state;
 
+#[cfg(feature = "query")]
+pub mod oobi;
 #[cfg(feature = "query")]
 pub mod query;
+#[cfg(feature = "async")]
+mod transport;
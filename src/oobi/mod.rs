@@ -0,0 +1,453 @@
+use std::{convert::TryFrom, fmt, str::FromStr, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{
+    database::sled::SledEventDatabase,
+    error::Error,
+    event_message::signed_event_message::Message,
+    event_parsing::message::signed_event_stream,
+    prefix::{IdentifierPrefix, Prefix},
+    processor::EventProcessor,
+    query::{
+        reply::{ReplyPayload, SignedReply},
+        Route,
+    },
+};
+
+/// Role of endpoint serving identifier.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Controller,
+    Witness,
+    Watcher,
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Role::Controller => "controller",
+            Role::Witness => "witness",
+            Role::Watcher => "watcher",
+        })
+    }
+}
+
+impl FromStr for Role {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "controller" => Ok(Role::Controller),
+            "witness" => Ok(Role::Witness),
+            "watcher" => Ok(Role::Watcher),
+            _ => Err(Error::SemanticError(format!("Unknown role: {}", s))),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Scheme {
+    Http,
+    Tcp,
+}
+
+/// Location Scheme
+///
+/// Url under which endpoint `eid` is reachable with given scheme. Carried
+/// in `/loc/scheme` reply signed by the endpoint.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LocationScheme {
+    pub eid: IdentifierPrefix,
+    pub scheme: Scheme,
+    pub url: Url,
+}
+
+impl LocationScheme {
+    pub fn new(eid: IdentifierPrefix, scheme: Scheme, url: Url) -> Self {
+        Self { eid, scheme, url }
+    }
+}
+
+/// End Role
+///
+/// Authorization of endpoint `eid` to play `role` for controller `cid`.
+/// Carried in `/end/role/add` and `/end/role/cut` replies signed by the
+/// controller.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct EndRole {
+    pub cid: IdentifierPrefix,
+    pub role: Role,
+    pub eid: IdentifierPrefix,
+}
+
+/// Out-Of-Band Introduction
+///
+/// Url of endpoint `eid` playing `role` for controller `cid`, in the form
+/// of `<url>/oobi/<cid>/<role>/<eid>`. Endpoint of controller role may be
+/// omitted, as in `<url>/oobi/<cid>/controller`, and so may the role, which
+/// then defaults to controller.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Oobi {
+    pub url: Url,
+    pub cid: IdentifierPrefix,
+    pub role: Role,
+    pub eid: Option<IdentifierPrefix>,
+}
+
+impl Oobi {
+    pub fn new(url: Url, cid: IdentifierPrefix, role: Role, eid: Option<IdentifierPrefix>) -> Self {
+        Self {
+            url,
+            cid,
+            role,
+            eid,
+        }
+    }
+
+    /// Returns url the introduction is resolved with.
+    pub fn to_url(&self) -> Result<Url, Error> {
+        let mut url = self.url.clone();
+        url.path_segments_mut()
+            .map_err(|_| Error::SemanticError("Improper OOBI base url".into()))?
+            .pop_if_empty()
+            .extend(&["oobi", &self.cid.to_str(), &self.role.to_string()])
+            .extend(self.eid.as_ref().map(|eid| eid.to_str()));
+        Ok(url)
+    }
+}
+
+impl fmt::Display for Oobi {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.to_url() {
+            Ok(url) => write!(f, "{}", url),
+            Err(_) => Err(fmt::Error),
+        }
+    }
+}
+
+impl FromStr for Oobi {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let improper = || Error::SemanticError(format!("Improper OOBI: {}", s));
+        let mut url = Url::parse(s).map_err(|_| improper())?;
+        let segments: Vec<String> = url
+            .path_segments()
+            .ok_or_else(improper)?
+            .map(str::to_string)
+            .collect();
+        let oobi_index = segments
+            .iter()
+            .rposition(|segment| segment == "oobi")
+            .ok_or_else(improper)?;
+        let (base, rest) = segments.split_at(oobi_index);
+        let (cid, role, eid) = match &rest[1..] {
+            [cid] => (cid, None, None),
+            [cid, role] => (cid, Some(role), None),
+            [cid, role, eid] => (cid, Some(role), Some(eid)),
+            _ => return Err(improper()),
+        };
+        url.set_query(None);
+        url.path_segments_mut()
+            .map_err(|_| improper())?
+            .clear()
+            .extend(base);
+        Ok(Oobi {
+            url,
+            cid: cid.parse()?,
+            role: role.map_or(Ok(Role::Controller), |role| role.parse())?,
+            eid: eid.map(|eid| eid.parse()).transpose()?,
+        })
+    }
+}
+
+/// Transport used to fetch CESR stream of introduced endpoint, so OOBIs can
+/// be resolved over any protocol.
+pub trait OobiTransport {
+    fn get(&self, url: &Url) -> Result<Vec<u8>, Error>;
+}
+
+/// OOBI Manager
+///
+/// Resolves OOBIs and keeps resulting endpoint data. Resolution fetches
+/// KEL of the controller together with signed `/loc/scheme` and
+/// `/end/role` replies, which are verified and saved.
+pub struct OobiManager {
+    processor: EventProcessor,
+}
+
+impl OobiManager {
+    pub fn new(db: Arc<SledEventDatabase>) -> Self {
+        Self {
+            processor: EventProcessor::new(db),
+        }
+    }
+
+    pub fn resolve(&self, oobi: &Oobi, transport: &dyn OobiTransport) -> Result<(), Error> {
+        let stream = transport.get(&oobi.to_url()?)?;
+        self.process_stream(&stream)?;
+        // nontransferable controllers, like witnesses, have no KEL
+        if !matches!(&oobi.cid, IdentifierPrefix::Basic(bp) if !bp.is_transferable())
+            && self.processor.compute_state(&oobi.cid)?.is_none()
+        {
+            return Err(Error::SemanticError(format!(
+                "OOBI didn't provide KEL of {}",
+                oobi.cid.to_str()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Processes KEL events, receipts and endpoint replies of the stream.
+    pub fn process_stream(&self, stream: &[u8]) -> Result<(), Error> {
+        let messages = signed_event_stream(stream)
+            .map_err(|e| Error::DeserializeError(e.to_string()))?
+            .1;
+        for message in messages {
+            match Message::try_from(message)? {
+                Message::KeyStateNotice(rpy) => self.process_reply(&rpy)?,
+                message => match self.processor.process(message) {
+                    Ok(_) | Err(Error::EventDuplicateError) => (),
+                    Err(e) => return Err(e),
+                },
+            };
+        }
+        Ok(())
+    }
+
+    /// Accepts `/loc/scheme` reply signed by the endpoint or `/end/role`
+    /// reply signed by the controller. Replies older than accepted ones are
+    /// rejected.
+    pub fn process_reply(&self, rpy: &SignedReply) -> Result<(), Error> {
+        match rpy.reply.event.get_route() {
+            Route::LocScheme | Route::EndRoleAdd | Route::EndRoleCut => {
+                self.processor.process_signed_reply(rpy).map(|_| ())
+            }
+            _ => Err(Error::SemanticError("Not an endpoint reply".into())),
+        }
+    }
+
+    pub fn get_loc_schemes(&self, eid: &IdentifierPrefix) -> Vec<LocationScheme> {
+        self.processor
+            .db
+            .get_loc_schemes(eid)
+            .into_iter()
+            .flatten()
+            .filter_map(|rpy| match rpy.reply.event.get_reply_data() {
+                ReplyPayload::LocScheme(loc_scheme) => Some(loc_scheme),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns endpoints currently authorized to play `role` for `cid`.
+    pub fn get_end_roles(&self, cid: &IdentifierPrefix, role: Role) -> Vec<EndRole> {
+        self.processor
+            .db
+            .get_end_roles(cid)
+            .into_iter()
+            .flatten()
+            .filter_map(|rpy| {
+                match (
+                    rpy.reply.event.get_route(),
+                    rpy.reply.event.get_reply_data(),
+                ) {
+                    (Route::EndRoleAdd, ReplyPayload::EndRole(end_role))
+                        if end_role.role == role =>
+                    {
+                        Some(end_role)
+                    }
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn test_oobi_url() -> Result<(), Error> {
+    let cid: IdentifierPrefix = "ELYk-z-SuTIeDncLr6GhwVUKnv3n3F1bF18qkXNd2bpk".parse()?;
+    let eid: IdentifierPrefix = "BVKaZVM5NSDEhAKqQrxFLxcwHTEQxKXS0MmldFzBW6mA".parse()?;
+
+    let oobi = Oobi::new(
+        Url::parse("http://127.0.0.1:5642/").unwrap(),
+        cid.clone(),
+        Role::Witness,
+        Some(eid.clone()),
+    );
+    let url = format!(
+        "http://127.0.0.1:5642/oobi/{}/witness/{}",
+        cid.to_str(),
+        eid.to_str()
+    );
+    assert_eq!(oobi.to_string(), url);
+    assert_eq!(url.parse::<Oobi>()?, oobi);
+
+    // role defaults to controller
+    let oobi: Oobi = format!("http://localhost/keri/oobi/{}", eid.to_str()).parse()?;
+    assert_eq!(oobi.role, Role::Controller);
+    assert_eq!(oobi.eid, None);
+    assert_eq!(
+        oobi.to_string(),
+        format!("http://localhost/keri/oobi/{}/controller", eid.to_str())
+    );
+
+    assert!("http://localhost/oobi".parse::<Oobi>().is_err());
+    assert!(format!("http://localhost/oobi/{}/owner", cid.to_str())
+        .parse::<Oobi>()
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn test_oobi_resolution() -> Result<(), Error> {
+    use crate::{
+        derivation::{self_addressing::SelfAddressing, self_signing::SelfSigning},
+        event::{sections::seal::EventSeal, SerializationFormats},
+        event_parsing::SignedEventData,
+        keri::{witness::Witness, Keri},
+        prefix::AttachedSignaturePrefix,
+        query::reply::ReplyEvent,
+        signer::{CryptoBox, KeyManager},
+    };
+    use std::sync::Mutex;
+    use tempfile::Builder;
+
+    struct StreamTransport(Vec<u8>);
+
+    impl OobiTransport for StreamTransport {
+        fn get(&self, _url: &Url) -> Result<Vec<u8>, Error> {
+            Ok(self.0.clone())
+        }
+    }
+
+    let witness_root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let witness = Witness::new(witness_root.path())?;
+    let witness_id = IdentifierPrefix::Basic(witness.prefix.clone());
+    let witness_url = Url::parse("http://127.0.0.1:5642/").unwrap();
+
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db = Arc::new(SledEventDatabase::new(root.path())?);
+    let key_manager = Arc::new(Mutex::new(CryptoBox::new()?));
+    let mut alice = Keri::new(db, Arc::clone(&key_manager))?;
+    let icp = alice.incept(Some(vec![witness.prefix.clone()]))?;
+
+    let end_role_reply = |route: Route| -> Result<SignedReply, Error> {
+        let end_role = EndRole {
+            cid: alice.prefix().clone(),
+            role: Role::Witness,
+            eid: witness_id.clone(),
+        };
+        let rpy = ReplyEvent::new_reply(
+            ReplyPayload::EndRole(end_role),
+            route,
+            SelfAddressing::Blake3_256,
+            SerializationFormats::JSON,
+        )?;
+        let signature = key_manager.lock().unwrap().sign(&rpy.serialize()?)?;
+        let seal = EventSeal {
+            prefix: alice.prefix().clone(),
+            sn: 0,
+            event_digest: icp.event_message.get_digest(),
+        };
+        Ok(SignedReply::new_trans(
+            rpy,
+            seal,
+            vec![AttachedSignaturePrefix::new(
+                SelfSigning::Ed25519Sha512,
+                signature,
+                0,
+            )],
+        ))
+    };
+
+    let oobi = Oobi::new(
+        witness_url.clone(),
+        alice.prefix().clone(),
+        Role::Witness,
+        Some(witness_id.clone()),
+    );
+    let stream = [
+        SignedEventData::from(&icp).to_cesr()?,
+        SignedEventData::from(end_role_reply(Route::EndRoleAdd)?).to_cesr()?,
+        SignedEventData::from(witness.make_loc_scheme(Scheme::Http, witness_url.clone())?)
+            .to_cesr()?,
+    ]
+    .concat();
+
+    let bob_root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let bob_db = Arc::new(SledEventDatabase::new(bob_root.path())?);
+    let manager = OobiManager::new(Arc::clone(&bob_db));
+
+    // stream without KEL doesn't resolve OOBI
+    assert!(manager.resolve(&oobi, &StreamTransport(vec![])).is_err());
+
+    manager.resolve(&oobi, &StreamTransport(stream))?;
+    assert_eq!(
+        EventProcessor::new(bob_db).compute_state(alice.prefix())?,
+        alice.get_state()?
+    );
+    let end_roles = manager.get_end_roles(alice.prefix(), Role::Witness);
+    assert_eq!(end_roles.len(), 1);
+    assert_eq!(end_roles[0].eid, witness_id);
+    assert!(manager
+        .get_end_roles(alice.prefix(), Role::Watcher)
+        .is_empty());
+    assert_eq!(
+        manager.get_loc_schemes(&witness_id),
+        vec![LocationScheme::new(
+            witness_id.clone(),
+            Scheme::Http,
+            witness_url.clone()
+        )]
+    );
+
+    // witness oobi
+    let witness_oobi = witness.oobi(witness_url.clone());
+    assert_eq!(
+        witness_oobi.to_string(),
+        format!(
+            "http://127.0.0.1:5642/oobi/{}/controller",
+            witness_id.to_str()
+        )
+    );
+    let new_url = Url::parse("http://localhost:5643/").unwrap();
+    let loc_scheme = witness.make_loc_scheme(Scheme::Http, new_url.clone())?;
+    manager.resolve(
+        &witness_oobi,
+        &StreamTransport(SignedEventData::from(loc_scheme).to_cesr()?),
+    )?;
+    assert_eq!(manager.get_loc_schemes(&witness_id)[0].url, new_url);
+
+    // endpoint role is removed
+    manager.process_reply(&end_role_reply(Route::EndRoleCut)?)?;
+    assert!(manager
+        .get_end_roles(alice.prefix(), Role::Witness)
+        .is_empty());
+
+    // location of other identifier can't be set by witness
+    let rpy = ReplyEvent::new_reply(
+        ReplyPayload::LocScheme(LocationScheme::new(
+            alice.prefix().clone(),
+            Scheme::Http,
+            witness_url,
+        )),
+        Route::LocScheme,
+        SelfAddressing::Blake3_256,
+        SerializationFormats::JSON,
+    )?;
+    let forged = SignedReply {
+        reply: rpy,
+        signature: witness.make_loc_scheme(Scheme::Tcp, new_url)?.signature,
+    };
+    assert!(manager.process_reply(&forged).is_err());
+    assert!(manager.get_loc_schemes(alice.prefix()).is_empty());
+
+    Ok(())
+}
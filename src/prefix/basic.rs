@@ -25,6 +25,10 @@ impl BasicPrefix {
     pub fn verify(&self, data: &[u8], signature: &SelfSigningPrefix) -> Result<bool, Error> {
         verify(data, self, signature)
     }
+
+    pub fn is_transferable(&self) -> bool {
+        self.derivation.is_transferable()
+    }
 }
 
 impl PartialEq for BasicPrefix {
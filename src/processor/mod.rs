@@ -1,8 +1,18 @@
 #[cfg(feature = "query")]
-use crate::query::{key_state_notice::KeyStateNotice, reply::SignedReply, QueryError};
-#[cfg(feature = "query")]
+use crate::query::{
+    exchange::SignedExchange,
+    key_state_notice::KeyStateNotice,
+    query::{QueryArgs, SignedQuery},
+    reply::{ReplyPayload, SignedReply},
+    router::{ExchangeRouter, ReplyRouter},
+    QueryError,
+};
 use chrono::{DateTime, FixedOffset};
+#[cfg(feature = "query")]
+use serde::de::DeserializeOwned;
 use std::sync::Arc;
+#[cfg(feature = "query")]
+use std::sync::RwLock;
 
 use crate::{
     database::sled::SledEventDatabase,
@@ -10,6 +20,7 @@ use crate::{
     event::{
         event_data::EventData,
         sections::{
+            configuration::ConfigurationTrait,
             seal::{EventSeal, Seal},
             KeyConfig,
         },
@@ -23,7 +34,8 @@ use crate::{
             TimestampedSignedEventMessage,
         },
     },
-    prefix::{IdentifierPrefix, SelfAddressingPrefix},
+    event_parsing::SignedEventData,
+    prefix::{BasicPrefix, IdentifierPrefix, SelfAddressingPrefix},
     state::{EventSemantics, IdentifierState},
 };
 
@@ -34,11 +46,21 @@ mod tests;
 
 pub struct EventProcessor {
     pub db: Arc<SledEventDatabase>,
+    #[cfg(feature = "query")]
+    reply_router: RwLock<ReplyRouter>,
+    #[cfg(feature = "query")]
+    exchange_router: RwLock<ExchangeRouter>,
 }
 
 impl EventProcessor {
     pub fn new(db: Arc<SledEventDatabase>) -> Self {
-        Self { db }
+        Self {
+            db,
+            #[cfg(feature = "query")]
+            reply_router: RwLock::new(ReplyRouter::default()),
+            #[cfg(feature = "query")]
+            exchange_router: RwLock::new(ExchangeRouter::default()),
+        }
     }
 
     /// Compute State for Prefix
@@ -150,6 +172,114 @@ impl EventProcessor {
         }
     }
 
+    /// Get KERL with Receipts for Prefix
+    ///
+    /// Returns the current validated KEL for a given Prefix, with every
+    /// event followed by witness receipts of it, merged into one receipt
+    pub fn get_kerl_with_receipts(&self, id: &IdentifierPrefix) -> Result<Option<Vec<u8>>, Error> {
+        self.get_kerl_tail_with_receipts(id, 0, None)
+    }
+
+    /// Get KERL Tail With Receipts
+    ///
+    /// Returns events of the validated KEL starting from `sn`, and first
+    /// seen after `dt` if given, with witness receipts as in
+    /// `get_kerl_with_receipts`. Lets the querier fetch only the events it
+    /// is missing.
+    pub fn get_kerl_tail_with_receipts(
+        &self,
+        id: &IdentifierPrefix,
+        sn: u64,
+        dt: Option<DateTime<FixedOffset>>,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        let events = match self.db.get_kel_finalized_events(id) {
+            Some(events) => events,
+            None => return Ok(None),
+        };
+        let tail = events
+            .filter(|event| event.signed_event_message.event_message.event.get_sn() >= sn)
+            .filter(|event| dt.is_none_or(|dt| event.timestamp > dt))
+            .map(|event| event.signed_event_message);
+        self.get_events_with_receipts(id, tail).map(Some)
+    }
+
+    /// Returns given events of `id` KEL, every one followed by witness
+    /// receipts of it, merged into one receipt.
+    pub fn get_events_with_receipts(
+        &self,
+        id: &IdentifierPrefix,
+        events: impl IntoIterator<Item = SignedEventMessage>,
+    ) -> Result<Vec<u8>, Error> {
+        let receipts = self
+            .db
+            .get_receipts_nt(id)
+            .map(|rcts| rcts.collect::<Vec<_>>())
+            .unwrap_or_default();
+        let mut kerl = vec![];
+        for event in events {
+            kerl.extend(SignedEventData::from(&event).to_cesr()?);
+            let sn = event.event_message.event.get_sn();
+            let mut event_receipts = receipts.iter().filter(|rct| rct.body.event.sn == sn);
+            if let Some(first) = event_receipts.next() {
+                let couplets = event_receipts.fold(first.couplets.clone(), |mut acc, rct| {
+                    for couplet in &rct.couplets {
+                        if !acc.iter().any(|(witness, _)| witness == &couplet.0) {
+                            acc.push(couplet.clone());
+                        }
+                    }
+                    acc
+                });
+                let rct = SignedNontransferableReceipt::new(&first.body, couplets);
+                kerl.extend(SignedEventData::from(rct).to_cesr()?);
+            }
+        }
+        Ok(kerl)
+    }
+
+    /// Get Queried KEL
+    ///
+    /// Returns events with receipts asked for by `log` query: the one
+    /// anchoring seal given in `args`, or KEL tail selected by `args`.
+    #[cfg(feature = "query")]
+    pub fn get_queried_kel(&self, args: &QueryArgs) -> Result<Vec<u8>, Error> {
+        match &args.a {
+            Some(seal) => {
+                let event = self
+                    .get_anchoring_event(&args.i, seal)?
+                    .ok_or_else(|| Error::SemanticError("No anchoring event".into()))?;
+                self.get_events_with_receipts(&args.i, vec![event])
+            }
+            None => self
+                .get_kerl_tail_with_receipts(&args.i, args.s.unwrap_or_default(), args.dt)?
+                .ok_or_else(|| Error::SemanticError("No identifier in db".into())),
+        }
+    }
+
+    /// Get Anchoring Event
+    ///
+    /// Returns event of `id` KEL which anchors `seal` in its data.
+    pub fn get_anchoring_event(
+        &self,
+        id: &IdentifierPrefix,
+        seal: &Seal,
+    ) -> Result<Option<SignedEventMessage>, Error> {
+        Ok(self
+            .db
+            .get_kel_finalized_events(id)
+            .into_iter()
+            .flatten()
+            .map(|event| event.signed_event_message)
+            .find(|event| {
+                let data = match event.event_message.event.get_event_data() {
+                    EventData::Icp(icp) => icp.data,
+                    EventData::Rot(rot) | EventData::Drt(rot) => rot.data,
+                    EventData::Ixn(ixn) => ixn.data,
+                    EventData::Dip(dip) => dip.inception_data.data,
+                };
+                data.contains(seal)
+            }))
+    }
+
     /// Get keys from Establishment Event
     ///
     /// Returns the current Key Config associated with
@@ -257,7 +387,9 @@ impl EventProcessor {
             #[cfg(feature = "query")]
             Message::KeyStateNotice(ksn_rpy) => self.process_signed_reply(&ksn_rpy),
             #[cfg(feature = "query")]
-            Message::Query(_qry) => todo!(),
+            Message::Query(qry) => self.verify_signed_query(&qry).map(|_| None),
+            #[cfg(feature = "query")]
+            Message::Exchange(exn) => self.process_signed_exchange(&exn).map(|_| None),
         }
     }
 
@@ -278,18 +410,50 @@ impl EventProcessor {
     /// Validates a Key Event against the latest state
     /// of the Identifier and applies it to update the state
     /// returns the updated state
+    /// Event with designated witnesses is logged only after reaching
+    /// witness agreement (see `has_agreement`), until then it's escrowed
+    /// and `NotEnoughReceiptsError` is returned. Its receipts processed
+    /// later may promote it.
     /// TODO improve checking and handling of errors!
     pub fn process_event(
         &self,
         signed_event: &SignedEventMessage,
     ) -> Result<Option<IdentifierState>, Error> {
-        // Log event.
+        self.validate_and_log(signed_event, true)
+    }
+
+    /// Process Own Event
+    ///
+    /// Like `process_event`, but logs event without waiting for witness
+    /// agreement. Meant for events of our own identifiers and for events
+    /// we witness, which have to be logged before being receipted.
+    pub fn process_own_event(
+        &self,
+        signed_event: &SignedEventMessage,
+    ) -> Result<Option<IdentifierState>, Error> {
+        self.validate_and_log(signed_event, false)
+    }
 
+    fn validate_and_log(
+        &self,
+        signed_event: &SignedEventMessage,
+        wait_for_agreement: bool,
+    ) -> Result<Option<IdentifierState>, Error> {
         let id = &signed_event.event_message.event.get_prefix();
 
         // If delegated event, check its delegator seal.
         match signed_event.event_message.event.get_event_data() {
             EventData::Dip(dip) => {
+                if let Some(delegator_state) = self.compute_state(&dip.delegator)? {
+                    if delegator_state
+                        .config
+                        .contains(&ConfigurationTrait::DoNotDelegate)
+                    {
+                        return Err(Error::SemanticError(
+                            "Delegator doesn't allow delegation".into(),
+                        ));
+                    }
+                }
                 let (sn, dig) = signed_event
                     .delegator_seal
                     .as_ref()
@@ -324,36 +488,125 @@ impl EventProcessor {
             }
             _ => Ok(()),
         }?;
-        self.apply_to_state(&signed_event.event_message)
-            .and_then(|new_state| {
-                // add event from the get go and clean it up on failure later
-                self.db.add_kel_finalized_event(signed_event.clone(), id)?;
-                // match on verification result
-                match new_state
-                    .current
-                    .verify(
-                        &signed_event.event_message.serialize()?,
-                        &signed_event.signatures,
-                    )
-                    .and_then(|result| {
-                        if !result {
-                            Err(Error::SignatureVerificationError)
-                        } else {
-                            // TODO should check if there are enough receipts and probably escrow
-                            Ok(new_state)
-                        }
-                    }) {
-                    Ok(state) => Ok(Some(state)),
-                    Err(e) => {
-                        if let Error::EventDuplicateError = e {
-                            self.db.add_duplicious_event(signed_event.clone(), id)?
-                        };
-                        // remove last added event
-                        self.db.remove_kel_finalized_event(id, signed_event)?;
-                        Err(e)
-                    }
+        let new_state = self.apply_to_state(&signed_event.event_message)?;
+        if !new_state.current.verify(
+            &signed_event.event_message.serialize()?,
+            &signed_event.signatures,
+        )? {
+            return Err(Error::SignatureVerificationError);
+        }
+        let receipts = self.escrowed_receipts_of(&signed_event.event_message, &new_state)?;
+        if wait_for_agreement && !Self::reaches_agreement(&new_state, receipts.iter().cloned()) {
+            // event waits in escrow for receipts of its designated witnesses
+            if !self
+                .db
+                .get_partially_witnessed_events(id)
+                .into_iter()
+                .flatten()
+                .any(|escrowed| &escrowed.signed_event_message == signed_event)
+            {
+                self.db
+                    .add_partially_witnessed_event(signed_event.clone(), id)?;
+            }
+            return Err(Error::NotEnoughReceiptsError);
+        }
+        self.log_event(signed_event, receipts)?;
+        Ok(Some(new_state))
+    }
+
+    /// Logs event into KEL, along with its escrowed receipts. Escrowed
+    /// events of the same sn can't be accepted anymore, so conflicting ones
+    /// are moved to duplicitous.
+    fn log_event(
+        &self,
+        event: &SignedEventMessage,
+        receipts: Vec<SignedNontransferableReceipt>,
+    ) -> Result<(), Error> {
+        let id = &event.event_message.event.get_prefix();
+        let sn = event.event_message.event.get_sn();
+        self.db.add_kel_finalized_event(event.clone(), id)?;
+        for rct in receipts {
+            self.db.remove_escrow_nt_receipt(id, &rct)?;
+            self.db.add_receipt_nt(rct, id)?;
+        }
+        let escrowed = self
+            .db
+            .get_partially_witnessed_events(id)
+            .into_iter()
+            .flatten()
+            .map(|escrowed| escrowed.signed_event_message)
+            .filter(|escrowed| escrowed.event_message.event.get_sn() == sn);
+        for escrowed in escrowed {
+            self.db.remove_partially_witnessed_event(id, &escrowed)?;
+            if &escrowed != event {
+                self.db.add_duplicious_event(escrowed, id)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns escrowed receipts of `event`, which resulted in `state`.
+    /// Only receipts of its designated witnesses with valid signatures are
+    /// returned.
+    fn escrowed_receipts_of(
+        &self,
+        event: &EventMessage<KeyEvent>,
+        state: &IdentifierState,
+    ) -> Result<Vec<SignedNontransferableReceipt>, Error> {
+        let receipts = self
+            .db
+            .get_escrow_nt_receipts(&state.prefix)
+            .into_iter()
+            .flatten()
+            .filter(|rct| {
+                rct.body.event.sn == state.sn
+                    && rct.body.event.receipted_event_digest == state.last_event_digest
+            });
+        let mut valid = vec![];
+        for rct in receipts {
+            if Self::verify_witness_receipt(&rct, event, &state.witnesses).is_ok() {
+                valid.push(rct);
+            }
+        }
+        Ok(valid)
+    }
+
+    /// Checks if `receipts` of last event of `state` are made by at least
+    /// `tally` of its designated witnesses.
+    fn reaches_agreement(
+        state: &IdentifierState,
+        receipts: impl IntoIterator<Item = SignedNontransferableReceipt>,
+    ) -> bool {
+        let mut witnessed: Vec<BasicPrefix> = vec![];
+        for rct in receipts {
+            if rct.body.event.sn != state.sn
+                || rct.body.event.receipted_event_digest != state.last_event_digest
+            {
+                continue;
+            }
+            for (witness, _) in rct.couplets {
+                if state.witnesses.contains(&witness) && !witnessed.contains(&witness) {
+                    witnessed.push(witness);
                 }
-            })
+            }
+        }
+        witnessed.len() as u64 >= state.tally
+    }
+
+    /// Has Agreement
+    ///
+    /// Checks if event of given sn reached witness agreement, which is
+    /// when it is receipted by at least `tally` of its designated witnesses.
+    /// Events of other identifiers are logged only after reaching it, so
+    /// conflicting events can't replace them.
+    pub fn has_agreement(&self, id: &IdentifierPrefix, sn: u64) -> Result<bool, Error> {
+        match self.compute_state_at_sn(id, sn)? {
+            Some(state) if state.prefix == *id && state.sn == sn => Ok(Self::reaches_agreement(
+                &state,
+                self.db.get_receipts_nt(id).into_iter().flatten(),
+            )),
+            _ => Ok(false),
+        }
     }
 
     /// Process Validator Receipt
@@ -403,18 +656,24 @@ impl EventProcessor {
         // get event which is being receipted
         let id = &rct.body.event.prefix.to_owned();
         if let Ok(Some(event)) = self.get_event_at_sn(&rct.body.event.prefix, rct.body.event.sn) {
-            let serialized_event = event.signed_event_message.serialize()?;
-            let (_, mut errors): (Vec<_>, Vec<Result<bool, Error>>) = rct
-                .clone()
-                .couplets
-                .into_iter()
-                .map(|(witness, receipt)| witness.verify(&serialized_event, &receipt))
-                .partition(Result::is_ok);
-            if errors.is_empty() {
-                self.db.add_receipt_nt(rct, id)?
-            } else {
-                let e = errors.pop().unwrap().unwrap_err();
-                return Err(e);
+            let witnesses = self
+                .compute_state_at_sn(id, rct.body.event.sn)?
+                .map(|state| state.witnesses)
+                .unwrap_or_default();
+            Self::verify_witness_receipt(
+                &rct,
+                &event.signed_event_message.event_message,
+                &witnesses,
+            )?;
+            self.db.add_receipt_nt(rct, id)?
+        } else if let Some(event) = self.get_escrowed_event(&rct)? {
+            // receipt of partially witnessed event may make it agreed
+            let state = self.apply_to_state(&event.event_message)?;
+            Self::verify_witness_receipt(&rct, &event.event_message, &state.witnesses)?;
+            self.db.add_escrow_nt_receipt(rct, id)?;
+            let receipts = self.escrowed_receipts_of(&event.event_message, &state)?;
+            if Self::reaches_agreement(&state, receipts.iter().cloned()) {
+                self.log_event(&event, receipts)?;
             }
         } else {
             self.db.add_escrow_nt_receipt(rct, id)?
@@ -422,6 +681,60 @@ impl EventProcessor {
         self.compute_state(id)
     }
 
+    /// Returns partially witnessed event receipted by `rct`, if escrowed.
+    fn get_escrowed_event(
+        &self,
+        rct: &SignedNontransferableReceipt,
+    ) -> Result<Option<SignedEventMessage>, Error> {
+        for escrowed in self
+            .db
+            .get_partially_witnessed_events(&rct.body.event.prefix)
+            .into_iter()
+            .flatten()
+        {
+            let event = &escrowed.signed_event_message.event_message;
+            if event.event.get_sn() == rct.body.event.sn
+                && event.check_digest(&rct.body.event.receipted_event_digest)?
+            {
+                return Ok(Some(escrowed.signed_event_message));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Checks that every couplet of `rct` is made by one of `witnesses`
+    /// and signs `event`.
+    fn verify_witness_receipt(
+        rct: &SignedNontransferableReceipt,
+        event: &EventMessage<KeyEvent>,
+        witnesses: &[BasicPrefix],
+    ) -> Result<(), Error> {
+        if rct
+            .couplets
+            .iter()
+            .any(|(witness, _)| !witnesses.contains(witness))
+        {
+            return Err(Error::SemanticError("Receipt from non-witness".into()));
+        }
+        let serialized_event = event.serialize()?;
+        let (_, mut errors): (Vec<_>, Vec<Result<(), Error>>) = rct
+            .clone()
+            .couplets
+            .into_iter()
+            .map(|(witness, receipt)| {
+                if witness.verify(&serialized_event, &receipt)? {
+                    Ok(())
+                } else {
+                    Err(Error::SignatureVerificationError)
+                }
+            })
+            .partition(Result::is_ok);
+        match errors.pop() {
+            Some(e) => e,
+            None => Ok(()),
+        }
+    }
+
     pub fn get_event_at_sn(
         &self,
         id: &IdentifierPrefix,
@@ -434,7 +747,10 @@ impl EventProcessor {
         }
     }
 
-    fn apply_to_state(&self, event: &EventMessage<KeyEvent>) -> Result<IdentifierState, Error> {
+    pub(crate) fn apply_to_state(
+        &self,
+        event: &EventMessage<KeyEvent>,
+    ) -> Result<IdentifierState, Error> {
         // get state for id (TODO cache?)
         self.compute_state(&event.event.get_prefix())
             // get empty state if there is no state yet
@@ -459,11 +775,8 @@ impl EventProcessor {
     }
 
     #[cfg(feature = "query")]
-    fn bada_logic(&self, new_rpy: &SignedReply) -> Result<(), Error> {
-        use crate::query::{reply::ReplyEvent, Route};
-        let accepted_replys = self
-            .db
-            .get_accepted_replys(&new_rpy.reply.event.get_prefix());
+    fn bada_logic(&self, new_rpy: &SignedReply, old_rpy: Option<SignedReply>) -> Result<(), Error> {
+        use crate::query::reply::ReplyEvent;
 
         // helper function for reply timestamps checking
         fn check_dts(new_rpy: &ReplyEvent, old_rpy: &ReplyEvent) -> Result<(), Error> {
@@ -486,12 +799,7 @@ impl EventProcessor {
                 //  B) If sn of new equals sn of old And date-time-stamp of new is
                 //     greater than old
 
-                // get last reply for prefix with route with sender_prefix
-                match accepted_replys.and_then(|mut o| {
-                    o.find(|r: &SignedReply| {
-                        r.reply.event.get_route() == Route::ReplyKsn(seal.prefix.clone())
-                    })
-                }) {
+                match old_rpy {
                     Some(old_rpy) => {
                         // check sns
                         let new_sn = seal.sn.clone();
@@ -515,14 +823,9 @@ impl EventProcessor {
                     None => Err(QueryError::NoSavedReply.into()),
                 }
             }
-            Signature::NonTransferable(bp, _sig) => {
+            Signature::NonTransferable(_bp, _sig) => {
                 //  If date-time-stamp of new is greater than old
-                match accepted_replys.and_then(|mut o| {
-                    o.find(|r| {
-                        r.reply.event.get_route()
-                            == Route::ReplyKsn(IdentifierPrefix::Basic(bp.clone()))
-                    })
-                }) {
+                match old_rpy {
                     Some(old_rpy) => check_dts(&new_rpy.reply.event, &old_rpy.reply.event),
                     None => Err(QueryError::NoSavedReply.into()),
                 }
@@ -530,6 +833,87 @@ impl EventProcessor {
         }
     }
 
+    /// Verify Signed Query
+    ///
+    /// Checks signatures of query against current keys of its signer.
+    /// Answering the query is up to the caller, e.g. `Witness`.
+    #[cfg(feature = "query")]
+    pub fn verify_signed_query(&self, qry: &SignedQuery) -> Result<(), Error> {
+        let kc = self
+            .compute_state(&qry.signer)?
+            .ok_or_else(|| Error::SemanticError("No identifier in db".into()))?
+            .current;
+        if kc.verify(&qry.envelope.serialize()?, &qry.signatures)? {
+            Ok(())
+        } else {
+            Err(Error::SignatureVerificationError)
+        }
+    }
+
+    /// Verify Signed Exchange
+    ///
+    /// Checks digest of `exn` message and its signatures against keys of
+    /// the last establishment event of the signer. Signatures made with
+    /// rotated out keys aren't accepted.
+    #[cfg(feature = "query")]
+    pub fn verify_signed_exchange(&self, exn: &SignedExchange) -> Result<(), Error> {
+        exn.exchange.check_digest()?;
+        let signer = exn.get_signer();
+        match self.get_last_establishment_event_seal(&signer)? {
+            None => Err(Error::SemanticError("No identifier in db".into())),
+            Some(seal) if seal != exn.signer_seal => {
+                Err(Error::SemanticError("Signed with stale keys".into()))
+            }
+            Some(_) => self.verify(&exn.exchange.serialize()?, &exn.get_signature()),
+        }
+    }
+
+    /// Verifies `exn` message and passes it to handler registered for its
+    /// route.
+    #[cfg(feature = "query")]
+    pub fn process_signed_exchange(&self, exn: &SignedExchange) -> Result<(), Error> {
+        let router = self
+            .exchange_router
+            .read()
+            .map_err(|_| Error::MutexPoisoned)?;
+        if !router.has_handler(&exn.exchange.event.get_route()) {
+            return Err(Error::SemanticError("No handler for exchange route".into()));
+        }
+        self.verify_signed_exchange(exn)?;
+        router.dispatch(exn)
+    }
+
+    #[cfg(feature = "query")]
+    pub fn has_exchange_handler(&self, route: &crate::query::Route) -> Result<bool, Error> {
+        Ok(self
+            .exchange_router
+            .read()
+            .map_err(|_| Error::MutexPoisoned)?
+            .has_handler(route))
+    }
+
+    /// Registers handler of `exn` messages with `route`. Message payload
+    /// is deserialized into `P` and passed to handler once the message is
+    /// verified.
+    #[cfg(feature = "query")]
+    pub fn register_exchange_handler<P, F>(&self, route: &str, handler: F) -> Result<(), Error>
+    where
+        P: DeserializeOwned,
+        F: Fn(&SignedExchange, P) -> Result<(), Error> + Send + Sync + 'static,
+    {
+        self.exchange_router
+            .write()
+            .map_err(|_| Error::MutexPoisoned)?
+            .register(route, handler)
+    }
+
+    /// Process Signed Reply
+    ///
+    /// Accepts reply if it's properly signed and newer than the last
+    /// accepted one with the same route (BADA policy). Replies of signers
+    /// with unknown KEL are escrowed. Accepted `/ksn` reply returns key
+    /// state of its identifier, endpoint and application replies are saved
+    /// and the latter are passed to handlers registered for their routes.
     #[cfg(feature = "query")]
     pub fn process_signed_reply(
         &self,
@@ -538,38 +922,148 @@ impl EventProcessor {
         use crate::query::Route;
 
         let route = rpy.reply.event.get_route();
-        // check if signature was made by ksn creator
-        if let Route::ReplyKsn(ref aid) = route {
-            if &rpy.signature.get_signer() != aid {
-                return Err(QueryError::Error("Wrong reply message signer".into()).into());
-            };
-            let verification_result = self.verify(&rpy.reply.serialize()?, &rpy.signature);
-            if let Err(Error::EventOutOfOrderError) = verification_result {
-                self.escrow_reply(&rpy)?;
-                return Err(Error::QueryError(QueryError::OutOfOrderEventError));
+        match route {
+            Route::ReplyKsn(ref aid) => {
+                // check if signature was made by ksn creator
+                if &rpy.signature.get_signer() != aid {
+                    return Err(QueryError::Error("Wrong reply message signer".into()).into());
+                };
+                // last accepted reply for prefix with route of the signer
+                let old_rpy = self.get_accepted_reply(&rpy.get_prefix(), &route);
+                self.accept_reply(rpy, old_rpy)?;
+                // now unpack ksn and check its details
+                let ksn = match rpy.reply.event.get_reply_data() {
+                    ReplyPayload::Ksn(ksn) => *ksn,
+                    _ => return Err(Error::SemanticError("Reply is not key state notice".into())),
+                };
+                let ksn_checking_result = self.check_ksn(&ksn, aid);
+                if let Err(Error::QueryError(QueryError::OutOfOrderEventError)) =
+                    ksn_checking_result
+                {
+                    self.escrow_reply(rpy)?;
+                };
+                ksn_checking_result?;
+                self.db
+                    .update_accepted_reply(rpy.clone(), &rpy.get_prefix())?;
+                Ok(Some(rpy.reply.event.get_state()?))
             }
-            verification_result?;
-            rpy.reply.check_digest()?;
-            let bada_result = self.bada_logic(&rpy);
-            match bada_result {
-                Err(Error::QueryError(QueryError::NoSavedReply)) => {
-                    // no previous rpy event to compare
-                    Ok(())
+            Route::LocScheme | Route::EndRoleAdd | Route::EndRoleCut => {
+                self.process_endpoint_reply(rpy)?;
+                Ok(None)
+            }
+            Route::Custom(_) => {
+                let router = self.reply_router.read().map_err(|_| Error::MutexPoisoned)?;
+                if !router.has_handler(&route) {
+                    return Err(Error::SemanticError("No handler for reply route".into()));
                 }
-                anything => anything,
-            }?;
-            // now unpack ksn and check its details
-            let ksn = rpy.reply.event.get_reply_data();
-            let ksn_checking_result = self.check_ksn(&ksn, aid);
-            if let Err(Error::QueryError(QueryError::OutOfOrderEventError)) = ksn_checking_result {
-                self.escrow_reply(&rpy)?;
-            };
-            ksn_checking_result?;
-            self.db
-                .update_accepted_reply(rpy.clone(), &rpy.reply.event.get_prefix())?;
-            Ok(Some(rpy.reply.event.get_state()))
-        } else {
-            Err(Error::SemanticError("wrong route type".into()))
+                // application data concerns its signer only
+                let id = rpy.signature.get_signer();
+                let old_rpy = self.get_accepted_reply(&id, &route);
+                self.accept_reply(rpy, old_rpy)?;
+                router.dispatch(rpy)?;
+                self.db.update_accepted_reply(rpy.clone(), &id)?;
+                Ok(None)
+            }
+            _ => Err(Error::SemanticError("wrong route type".into())),
+        }
+    }
+
+    /// Registers handler of replies with application `route`. Reply data
+    /// is deserialized into `P` and passed to handler once the reply is
+    /// accepted.
+    #[cfg(feature = "query")]
+    pub fn register_reply_handler<P, F>(&self, route: &str, handler: F) -> Result<(), Error>
+    where
+        P: DeserializeOwned,
+        F: Fn(&SignedReply, P) -> Result<(), Error> + Send + Sync + 'static,
+    {
+        self.reply_router
+            .write()
+            .map_err(|_| Error::MutexPoisoned)?
+            .register(route, handler)
+    }
+
+    /// Checks signature and digest of reply and compares it with
+    /// `old_rpy`, the last accepted one with the same route. Replies
+    /// which can't be verified yet are escrowed.
+    #[cfg(feature = "query")]
+    fn accept_reply(&self, rpy: &SignedReply, old_rpy: Option<SignedReply>) -> Result<(), Error> {
+        let verification_result = self.verify(&rpy.reply.serialize()?, &rpy.signature);
+        if let Err(Error::EventOutOfOrderError) = verification_result {
+            self.escrow_reply(rpy)?;
+            return Err(Error::QueryError(QueryError::OutOfOrderEventError));
+        }
+        verification_result?;
+        rpy.reply.check_digest()?;
+        match self.bada_logic(rpy, old_rpy) {
+            Err(Error::QueryError(QueryError::NoSavedReply)) => {
+                // no previous rpy event to compare
+                Ok(())
+            }
+            anything => anything,
+        }
+    }
+
+    #[cfg(feature = "query")]
+    fn get_accepted_reply(
+        &self,
+        id: &IdentifierPrefix,
+        route: &crate::query::Route,
+    ) -> Option<SignedReply> {
+        self.db
+            .get_accepted_replys(id)
+            .and_then(|mut rpys| rpys.find(|old| &old.reply.event.get_route() == route))
+    }
+
+    /// Process Endpoint Reply
+    ///
+    /// Accepts `/loc/scheme` reply signed by the endpoint or `/end/role`
+    /// reply signed by the controller, if it's newer than the accepted one
+    /// for the same scheme, or the same role and endpoint.
+    #[cfg(feature = "query")]
+    fn process_endpoint_reply(&self, rpy: &SignedReply) -> Result<(), Error> {
+        use crate::query::Route;
+
+        let id = rpy.get_prefix();
+        if rpy.signature.get_signer() != id {
+            return Err(QueryError::Error("Wrong reply message signer".into()).into());
+        };
+        let data = rpy.reply.event.get_reply_data();
+        match (rpy.reply.event.get_route(), &data) {
+            (Route::LocScheme, ReplyPayload::LocScheme(_))
+            | (Route::EndRoleAdd, ReplyPayload::EndRole(_))
+            | (Route::EndRoleCut, ReplyPayload::EndRole(_)) => (),
+            _ => {
+                return Err(Error::SemanticError(
+                    "Reply data doesn't match route".into(),
+                ))
+            }
+        };
+
+        let old_rpy = match &data {
+            ReplyPayload::LocScheme(loc_scheme) => {
+                self.db.get_loc_schemes(&id).and_then(|mut rpys| {
+                    rpys.find(|old| match old.reply.event.get_reply_data() {
+                        ReplyPayload::LocScheme(old) => old.scheme == loc_scheme.scheme,
+                        _ => false,
+                    })
+                })
+            }
+            ReplyPayload::EndRole(end_role) => self.db.get_end_roles(&id).and_then(|mut rpys| {
+                rpys.find(|old| match old.reply.event.get_reply_data() {
+                    ReplyPayload::EndRole(old) => {
+                        old.role == end_role.role && old.eid == end_role.eid
+                    }
+                    _ => false,
+                })
+            }),
+            _ => None,
+        };
+        self.accept_reply(rpy, old_rpy)?;
+
+        match data {
+            ReplyPayload::LocScheme(_) => self.db.update_loc_scheme(rpy.clone(), &id),
+            _ => self.db.update_end_role(rpy.clone(), &id),
         }
     }
 
@@ -646,7 +1140,7 @@ impl EventProcessor {
 
     #[cfg(feature = "query")]
     fn escrow_reply(&self, rpy: &SignedReply) -> Result<(), Error> {
-        let id = rpy.reply.event.get_prefix();
+        let id = rpy.get_prefix();
         self.db.add_escrowed_reply(rpy.clone(), &id)
     }
 
@@ -660,7 +1154,7 @@ impl EventProcessor {
                     | Err(Error::QueryError(QueryError::StaleRpy)) => {
                         // remove from escrow
                         self.db
-                            .remove_escrowed_reply(&sig_rep.reply.event.get_prefix(), sig_rep)
+                            .remove_escrowed_reply(&sig_rep.get_prefix(), sig_rep)
                             .unwrap();
                     }
                     Err(_e) => {} // keep in escrow,
@@ -0,0 +1,147 @@
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    derivation::self_addressing::SelfAddressing,
+    error::Error,
+    event::{sections::seal::EventSeal, EventMessage, SerializationFormats},
+    event_message::{
+        dummy_event::DummyEventMessage, signature::Signature, Digestible, EventTypeTag, SaidEvent,
+        Typeable,
+    },
+    prefix::{AttachedSignaturePrefix, IdentifierPrefix},
+};
+
+use super::{Envelope, EnvelopeData, Route};
+
+/// Exchange Data
+///
+/// Modifiers (`q`) and payload (`a`) of `exn` message. Both are kept as
+/// is, their meaning is determined by the message route.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct ExchangeData {
+    #[serde(rename = "q")]
+    pub modifiers: serde_json::Value,
+
+    #[serde(rename = "a")]
+    pub data: serde_json::Value,
+}
+
+impl Typeable for ExchangeData {
+    fn get_type(&self) -> EventTypeTag {
+        EventTypeTag::Exn
+    }
+}
+
+impl EnvelopeData for ExchangeData {}
+
+pub type ExchangeEvent = SaidEvent<Envelope<ExchangeData>>;
+
+impl ExchangeEvent {
+    pub fn new_exchange(
+        route: Route,
+        modifiers: serde_json::Value,
+        data: serde_json::Value,
+        self_addressing: SelfAddressing,
+        serialization: SerializationFormats,
+    ) -> Result<EventMessage<ExchangeEvent>, Error> {
+        let env = Envelope::new(route, ExchangeData { modifiers, data });
+        env.to_message(serialization, &self_addressing)
+    }
+
+    pub fn get_timestamp(&self) -> DateTime<FixedOffset> {
+        self.content.timestamp
+    }
+
+    pub fn get_route(&self) -> Route {
+        self.content.route.clone()
+    }
+
+    /// Returns recipient of the message, given by `pre` modifier.
+    pub fn get_recipient(&self) -> Option<IdentifierPrefix> {
+        self.content
+            .data
+            .modifiers
+            .get("pre")?
+            .as_str()?
+            .parse()
+            .ok()
+    }
+
+    pub fn get_modifiers(&self) -> serde_json::Value {
+        self.content.data.modifiers.clone()
+    }
+
+    pub fn get_data(&self) -> serde_json::Value {
+        self.content.data.data.clone()
+    }
+}
+
+impl EventMessage<ExchangeEvent> {
+    pub fn check_digest(&self) -> Result<(), Error> {
+        let dummy = DummyEventMessage::dummy_event(
+            self.event.clone(),
+            self.serialization_info.kind,
+            &self.event.get_digest().derivation,
+        )?
+        .serialize()?;
+        self.event
+            .get_digest()
+            .verify_binding(&dummy)
+            .then_some(())
+            .ok_or(Error::IncorrectDigest)
+    }
+}
+
+/// Signed Exchange
+///
+/// `exn` message signed by transferable identifier with keys of its
+/// establishment event designated by `signer_seal`. Attached as `-F`
+/// transferable signatures group.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignedExchange {
+    pub exchange: EventMessage<ExchangeEvent>,
+    pub signer_seal: EventSeal,
+    pub signatures: Vec<AttachedSignaturePrefix>,
+}
+
+impl SignedExchange {
+    pub fn new(
+        exchange: EventMessage<ExchangeEvent>,
+        signer_seal: EventSeal,
+        signatures: Vec<AttachedSignaturePrefix>,
+    ) -> Self {
+        Self {
+            exchange,
+            signer_seal,
+            signatures,
+        }
+    }
+
+    pub fn get_signer(&self) -> IdentifierPrefix {
+        self.signer_seal.prefix.clone()
+    }
+
+    pub fn get_signature(&self) -> Signature {
+        Signature::Transferable(self.signer_seal.clone(), self.signatures.clone())
+    }
+}
+
+#[test]
+fn test_exchange_serialization() -> Result<(), Error> {
+    let exn = ExchangeEvent::new_exchange(
+        Route::Custom("/multisig/icp".into()),
+        serde_json::json!({}),
+        serde_json::json!({"aids": ["EJccSRTfXYF6wrUVuenAIHzwcx3hJugeiJsEKmndi5q1"]}),
+        SelfAddressing::Blake3_256,
+        SerializationFormats::JSON,
+    )?;
+    let serialized = String::from_utf8(exn.serialize()?).unwrap();
+    assert!(serialized.contains(r#""t":"exn""#));
+    assert!(serialized.contains(r#""r":"/multisig/icp","q":{},"a":{"aids""#));
+
+    let deserialized: EventMessage<ExchangeEvent> = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(deserialized.get_digest(), exn.get_digest());
+    deserialized.check_digest()?;
+    Ok(())
+}
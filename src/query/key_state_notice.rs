@@ -4,7 +4,7 @@ use serde_hex::{Compact, SerHex};
 
 use crate::{
     event::SerializationFormats, event_message::serialization_info::SerializationInfo,
-    state::IdentifierState,
+    prefix::Prefix, state::IdentifierState,
 };
 
 #[derive(Clone, Debug, Deserialize, PartialEq)]
@@ -20,9 +20,6 @@ pub struct KeyStateNotice {
 
     #[serde(rename = "dt")]
     pub timestamp: DateTime<FixedOffset>,
-
-    #[serde(rename = "c")]
-    config: Vec<String>,
 }
 
 impl Serialize for KeyStateNotice {
@@ -46,11 +43,17 @@ impl Serialize for KeyStateNotice {
         em.serialize_field("k", &self.state.current.public_keys)?;
         em.serialize_field(
             "n",
-            &self.state.current.threshold_key_digest.clone().unwrap(),
+            &self
+                .state
+                .current
+                .threshold_key_digest
+                .as_ref()
+                .map(|n| n.to_str())
+                .unwrap_or_default(),
         )?;
         em.serialize_field("bt", &self.state.tally.to_string())?;
         em.serialize_field("b", &self.state.witnesses)?;
-        em.serialize_field("c", &self.config)?;
+        em.serialize_field("c", &self.state.config)?;
         em.serialize_field("ee", &self.state.last_est)?;
         em.serialize_field("di", &self.state.delegator.clone().unwrap_or_default())?;
         em.end()
@@ -66,9 +69,26 @@ impl KeyStateNotice {
             timestamp: dt,
             state,
             first_seen_sn: 0,
-            config: vec![],
         };
 
         ksn.clone()
     }
 }
+
+#[test]
+fn test_ksn_config_roundtrip() -> Result<(), crate::error::Error> {
+    use crate::event::sections::configuration::ConfigurationTrait;
+
+    let ksn = r#"{"v":"KERI10JSON0001d7_","i":"Et78eYkh8A3H9w6Q87EC5OcijiVEJT8KyNtEGdpPVWV8","s":"3","p":"EYhzp9WCvSNFT2dVryQpVFiTzuWGbFNhVHNKCqAqBI8A","d":"EsL4LnyvTGBqdYC_Ute3ag4XYbu8PdCj70un885pMYpA","f":"3","dt":"2021-01-01T00:00:00.000000+00:00","et":"rot","kt":"1","k":["DrcAz_gmDTuWIHn_mOQDeSK_aJIRiw5IMzPD7igzEDb0"],"n":"E_Y2NMHE0nqrTQLe57VPcM0razmxdxRVbljRCSetdjjI","bt":"0","b":[],"c":["EO","DND"],"ee":{"s":"3","d":"EsL4LnyvTGBqdYC_Ute3ag4XYbu8PdCj70un885pMYpA","br":[],"ba":[]}}"#;
+    let ksn: KeyStateNotice = serde_json::from_str(ksn)?;
+    assert_eq!(
+        ksn.state.config,
+        vec![
+            ConfigurationTrait::EstablishmentOnly,
+            ConfigurationTrait::DoNotDelegate
+        ]
+    );
+    let parsed: KeyStateNotice = serde_json::from_slice(&serde_json::to_vec(&ksn)?)?;
+    assert_eq!(parsed, ksn);
+    Ok(())
+}
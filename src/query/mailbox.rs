@@ -0,0 +1,178 @@
+use std::collections::BTreeMap;
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// Receipts of controller's events made by validators.
+pub const RECEIPT_TOPIC: &str = "/receipt";
+/// Partially signed events of multisig group and `/multisig` messages of
+/// its members.
+pub const MULTISIG_TOPIC: &str = "/multisig";
+/// Delegated events waiting for delegator's approval.
+pub const DELEGATE_TOPIC: &str = "/delegate";
+
+/// Returns topic of `exn` messages with `route`: its first segment, like
+/// `/multisig` for `/multisig/icp`.
+pub fn exchange_topic(route: &str) -> String {
+    let segment = route.trim_start_matches('/').split('/').next();
+    ["/", segment.unwrap_or_default()].join("")
+}
+
+/// Message waiting in mailbox of its recipient, stored as CESR stream.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MailboxMessage {
+    pub topic: String,
+    pub message: Vec<u8>,
+}
+
+/// Mailbox Response
+///
+/// Messages of queried topics, starting from cursors given in `mbx` query,
+/// and cursors which should be used in the next query. Sent to recipient
+/// as `/mbx` reply, with topic and index within the topic attached to
+/// every message.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MailboxResponse {
+    pub topics: BTreeMap<String, Vec<Vec<u8>>>,
+    pub cursors: BTreeMap<String, u64>,
+}
+
+impl MailboxResponse {
+    /// Returns messages of all topics as one CESR stream.
+    pub fn to_cesr(&self) -> Vec<u8> {
+        self.topics.values().flatten().flatten().copied().collect()
+    }
+}
+
+/// Message of mailbox response with its topic and index within the topic.
+/// Message is base64 encoded, as it may be serialized to binary format,
+/// like CBOR.
+#[derive(Serialize, Deserialize)]
+struct IndexedMessage {
+    topic: String,
+    idx: u64,
+    msg: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SerializedMailbox {
+    msgs: Vec<IndexedMessage>,
+    cursors: BTreeMap<String, u64>,
+}
+
+impl Serialize for MailboxResponse {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut msgs = vec![];
+        for (topic, messages) in &self.topics {
+            // messages of topic end just before its cursor
+            let first = self
+                .cursors
+                .get(topic)
+                .copied()
+                .unwrap_or_default()
+                .saturating_sub(messages.len() as u64);
+            for (idx, message) in (first..).zip(messages) {
+                msgs.push(IndexedMessage {
+                    topic: topic.clone(),
+                    idx,
+                    msg: base64::encode_config(message, base64::URL_SAFE_NO_PAD),
+                });
+            }
+        }
+        SerializedMailbox {
+            msgs,
+            cursors: self.cursors.clone(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for MailboxResponse {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let serialized = SerializedMailbox::deserialize(deserializer)?;
+        let mut topics: BTreeMap<String, Vec<Vec<u8>>> = serialized
+            .cursors
+            .keys()
+            .map(|topic| (topic.clone(), vec![]))
+            .collect();
+        // index of the last message of every topic
+        let mut last: BTreeMap<&str, u64> = BTreeMap::new();
+        for message in &serialized.msgs {
+            let messages = topics
+                .get_mut(&message.topic)
+                .ok_or_else(|| de::Error::custom("Message of topic without cursor"))?;
+            if last
+                .get(message.topic.as_str())
+                .is_some_and(|idx| idx + 1 != message.idx)
+            {
+                return Err(de::Error::custom("Mailbox messages out of order"));
+            }
+            last.insert(&message.topic, message.idx);
+            messages.push(
+                base64::decode_config(&message.msg, base64::URL_SAFE_NO_PAD)
+                    .map_err(de::Error::custom)?,
+            );
+        }
+        // cursor points just past the last message
+        if last
+            .iter()
+            .any(|(topic, idx)| serialized.cursors.get(*topic) != Some(&(idx + 1)))
+        {
+            return Err(de::Error::custom("Mailbox messages out of order"));
+        }
+        Ok(MailboxResponse {
+            topics,
+            cursors: serialized.cursors,
+        })
+    }
+}
+
+#[test]
+fn test_mailbox_serialization() -> Result<(), crate::error::Error> {
+    let mbx = MailboxResponse {
+        topics: vec![
+            (RECEIPT_TOPIC.to_string(), vec![b"rct".to_vec()]),
+            (DELEGATE_TOPIC.to_string(), vec![]),
+            (
+                MULTISIG_TOPIC.to_string(),
+                vec![b"ixn".to_vec(), b"rot".to_vec()],
+            ),
+        ]
+        .into_iter()
+        .collect(),
+        cursors: vec![
+            (RECEIPT_TOPIC.to_string(), 1),
+            (DELEGATE_TOPIC.to_string(), 0),
+            (MULTISIG_TOPIC.to_string(), 5),
+        ]
+        .into_iter()
+        .collect(),
+    };
+    let serialized = serde_json::to_string(&mbx)?;
+    assert!(serialized.contains(r#"{"topic":"/multisig","idx":3,"msg":"aXhu"}"#));
+    assert!(serialized.contains(r#"{"topic":"/multisig","idx":4,"msg":"cm90"}"#));
+    assert_eq!(serde_json::from_str::<MailboxResponse>(&serialized)?, mbx);
+
+    // binary messages, like CBOR ones, are kept intact
+    let cbor = serde_cbor::to_vec(&serde_json::json!({ "t": "rct" }))?;
+    assert!(String::from_utf8(cbor.clone()).is_err());
+    let mut binary = mbx.clone();
+    binary.topics.insert(RECEIPT_TOPIC.to_string(), vec![cbor]);
+    let binary_serialized = serde_json::to_string(&binary)?;
+    assert_eq!(
+        serde_json::from_str::<MailboxResponse>(&binary_serialized)?,
+        binary
+    );
+
+    // messages which don't follow cursors are rejected
+    let reordered = serialized.replace(r#""idx":3"#, r#""idx":5"#);
+    assert!(serde_json::from_str::<MailboxResponse>(&reordered).is_err());
+    let missing = serialized.replace(r#",{"topic":"/multisig","idx":4,"msg":"cm90"}"#, "");
+    assert!(serde_json::from_str::<MailboxResponse>(&missing).is_err());
+    Ok(())
+}
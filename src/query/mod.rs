@@ -5,20 +5,29 @@ use crate::{
     event_message::{EventTypeTag, SaidEvent, Typeable},
     prefix::{IdentifierPrefix, Prefix},
 };
+use std::convert::TryFrom;
+
 use chrono::{DateTime, FixedOffset, SecondsFormat, Utc};
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
-use self::reply::SignedReply;
+use self::{mailbox::MailboxResponse, reply::SignedReply};
 
 use thiserror::Error;
 
+pub mod exchange;
 pub mod key_state_notice;
+pub mod mailbox;
 pub mod query;
 pub mod reply;
+pub mod router;
 
 pub type TimeStamp = DateTime<FixedOffset>;
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(
+    try_from = "RawEnvelope<D>",
+    bound(deserialize = "D: Deserialize<'de> + EnvelopeData")
+)]
 pub struct Envelope<D: Serialize + Typeable> {
     #[serde(rename = "dt", serialize_with = "serialize_timestamp")]
     pub timestamp: DateTime<FixedOffset>,
@@ -30,6 +39,41 @@ pub struct Envelope<D: Serialize + Typeable> {
     pub data: D,
 }
 
+/// Envelope Data
+///
+/// Data of envelope message, which meaning may depend on the route. It's
+/// deserialized apart from the route, so it's given the route afterwards
+/// and fails when it doesn't fit it.
+pub trait EnvelopeData: Sized {
+    fn with_route(self, _route: &Route) -> Result<Self, Error> {
+        Ok(self)
+    }
+}
+
+#[derive(Deserialize)]
+struct RawEnvelope<D> {
+    #[serde(rename = "dt")]
+    timestamp: DateTime<FixedOffset>,
+
+    #[serde(rename = "r")]
+    route: Route,
+
+    #[serde(flatten)]
+    data: D,
+}
+
+impl<D: Serialize + Typeable + EnvelopeData> TryFrom<RawEnvelope<D>> for Envelope<D> {
+    type Error = Error;
+
+    fn try_from(raw: RawEnvelope<D>) -> Result<Self, Error> {
+        Ok(Envelope {
+            timestamp: raw.timestamp,
+            data: raw.data.with_route(&raw.route)?,
+            route: raw.route,
+        })
+    }
+}
+
 fn serialize_timestamp<S>(timestamp: &DateTime<FixedOffset>, s: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
@@ -66,7 +110,26 @@ impl<D: Serialize + Typeable> Typeable for Envelope<D> {
 pub enum Route {
     Log,
     Ksn,
+    Mbx,
     ReplyKsn(IdentifierPrefix),
+    /// Reply carrying messages from mailbox, answering `mbx` query.
+    ReplyMbx,
+    LocScheme,
+    EndRoleAdd,
+    EndRoleCut,
+    /// Route of application data, handled by registered reply handler.
+    Custom(String),
+}
+
+impl Route {
+    /// Returns application route of `route`. Routes of KERI messages are
+    /// reserved, as data under them is processed by KERI itself.
+    pub fn custom(route: &str) -> Result<Self, Error> {
+        match serde_json::from_value(serde_json::Value::String(route.into())) {
+            Ok(Route::Custom(route)) => Ok(Route::Custom(route)),
+            _ => Err(Error::SemanticError(format!("Reserved route: {}", route))),
+        }
+    }
 }
 
 impl Serialize for Route {
@@ -77,7 +140,13 @@ impl Serialize for Route {
         serializer.serialize_str(&match self {
             Route::Log => "log".into(),
             Route::Ksn => "ksn".into(),
+            Route::Mbx => "mbx".into(),
             Route::ReplyKsn(id) => ["/ksn/", &id.to_str()].join(""),
+            Route::ReplyMbx => "/mbx".into(),
+            Route::LocScheme => "/loc/scheme".into(),
+            Route::EndRoleAdd => "/end/role/add".into(),
+            Route::EndRoleCut => "/end/role/cut".into(),
+            Route::Custom(route) => route.clone(),
         })
     }
 }
@@ -88,14 +157,22 @@ impl<'de> Deserialize<'de> for Route {
         D: Deserializer<'de>,
     {
         let s = String::deserialize(deserializer)?;
-        if s.starts_with("/ksn/") {
-            let id: &IdentifierPrefix = &s[5..].parse().unwrap();
-            Ok(Route::ReplyKsn(id.clone()))
+        if let Some(id) = s.strip_prefix("/ksn/") {
+            let id = id.parse().map_err(de::Error::custom)?;
+            Ok(Route::ReplyKsn(id))
         } else {
             match &s[..] {
                 "ksn" => Ok(Route::Ksn),
                 "log" => Ok(Route::Log),
-                _ => Err(Error::SemanticError("".into())).map_err(de::Error::custom),
+                "mbx" => Ok(Route::Mbx),
+                "/mbx" => Ok(Route::ReplyMbx),
+                "/loc/scheme" => Ok(Route::LocScheme),
+                "/end/role/add" => Ok(Route::EndRoleAdd),
+                "/end/role/cut" => Ok(Route::EndRoleCut),
+                "" => Err(de::Error::custom(Error::SemanticError(
+                    "Empty route".into(),
+                ))),
+                _ => Ok(Route::Custom(s)),
             }
         }
     }
@@ -105,6 +182,7 @@ impl<'de> Deserialize<'de> for Route {
 pub enum ReplyType {
     Rep(SignedReply),
     Kel(Vec<u8>),
+    Mbx(MailboxResponse),
 }
 
 #[derive(Error, Debug)]
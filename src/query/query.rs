@@ -1,14 +1,17 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
+use serde_hex::{Compact, SerHexOpt};
 
 use crate::{
     derivation::self_addressing::SelfAddressing,
     error::Error,
-    event::{EventMessage, SerializationFormats},
+    event::{sections::seal::Seal, EventMessage, SerializationFormats},
     event_message::{EventTypeTag, SaidEvent, Typeable},
     prefix::{AttachedSignaturePrefix, IdentifierPrefix},
 };
 
-use super::{Envelope, Route};
+use super::{Envelope, EnvelopeData, Route, TimeStamp};
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct QueryData {
@@ -19,9 +22,82 @@ pub struct QueryData {
     pub data: QueryArgs,
 }
 
+/// Query Arguments
+///
+/// Identifier `i` the query is about, optionally narrowed to events from
+/// sn `s`, events first seen after `dt` or the event anchoring seal `a`.
+/// `src` is the witness the query is addressed to. Mailbox queries give
+/// cursor of every requested topic in `topics`.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct QueryArgs {
     pub i: IdentifierPrefix,
+
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "SerHexOpt::<Compact>"
+    )]
+    pub s: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dt: Option<TimeStamp>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub src: Option<IdentifierPrefix>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub a: Option<Seal>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub topics: Option<BTreeMap<String, u64>>,
+}
+
+impl QueryArgs {
+    pub fn new(i: IdentifierPrefix) -> Self {
+        Self {
+            i,
+            s: None,
+            dt: None,
+            src: None,
+            a: None,
+            topics: None,
+        }
+    }
+
+    pub fn with_sn(self, sn: u64) -> Self {
+        Self {
+            s: Some(sn),
+            ..self
+        }
+    }
+
+    pub fn with_timestamp(self, dt: TimeStamp) -> Self {
+        Self {
+            dt: Some(dt),
+            ..self
+        }
+    }
+
+    pub fn with_source(self, src: IdentifierPrefix) -> Self {
+        Self {
+            src: Some(src),
+            ..self
+        }
+    }
+
+    pub fn with_anchor(self, seal: Seal) -> Self {
+        Self {
+            a: Some(seal),
+            ..self
+        }
+    }
+
+    pub fn with_topics(self, topics: BTreeMap<String, u64>) -> Self {
+        Self {
+            topics: Some(topics),
+            ..self
+        }
+    }
 }
 
 pub type QueryEvent = SaidEvent<Envelope<QueryData>>;
@@ -32,10 +108,28 @@ impl QueryEvent {
         id: &IdentifierPrefix,
         serialization_format: SerializationFormats,
         derivation: &SelfAddressing,
+    ) -> Result<EventMessage<Self>, Error> {
+        Self::new_query_with_args(
+            route,
+            QueryArgs::new(id.clone()),
+            "",
+            serialization_format,
+            derivation,
+        )
+    }
+
+    /// Makes query with given arguments. Replies are requested to be sent
+    /// with `reply_route`, empty for the default one.
+    pub fn new_query_with_args(
+        route: Route,
+        args: QueryArgs,
+        reply_route: &str,
+        serialization_format: SerializationFormats,
+        derivation: &SelfAddressing,
     ) -> Result<EventMessage<Self>, Error> {
         let message = QueryData {
-            reply_route: "route".into(),
-            data: QueryArgs { i: id.clone() },
+            reply_route: reply_route.into(),
+            data: args,
         };
 
         let env = Envelope::new(route, message);
@@ -57,6 +151,8 @@ impl Typeable for QueryData {
     }
 }
 
+impl EnvelopeData for QueryData {}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct SignedQuery {
     pub envelope: EventMessage<QueryEvent>,
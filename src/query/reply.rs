@@ -1,5 +1,5 @@
 use chrono::{DateTime, FixedOffset};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
 use crate::{
     derivation::self_addressing::SelfAddressing,
@@ -9,16 +9,70 @@ use crate::{
         dummy_event::DummyEventMessage, signature::Signature, Digestible, EventTypeTag, SaidEvent,
         Typeable,
     },
+    oobi::{EndRole, LocationScheme},
     prefix::{AttachedSignaturePrefix, BasicPrefix, IdentifierPrefix, SelfSigningPrefix},
     state::IdentifierState,
 };
 
-use super::{key_state_notice::KeyStateNotice, Envelope, Route};
+use super::{
+    key_state_notice::KeyStateNotice, mailbox::MailboxResponse, Envelope, EnvelopeData, Route,
+};
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub struct ReplyData {
     #[serde(rename = "a")]
-    pub data: KeyStateNotice,
+    pub data: ReplyPayload,
+}
+
+/// Reply Payload
+///
+/// Data carried in `a` field of `rpy` message. Its kind is determined by
+/// reply route, not by its shape, and reply whose data doesn't fit its
+/// KERI route is rejected. Data of other routes is kept as is, to be
+/// deserialized by reply handler registered for the route.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(untagged)]
+pub enum ReplyPayload {
+    Ksn(Box<KeyStateNotice>),
+    EndRole(EndRole),
+    LocScheme(LocationScheme),
+    Mbx(MailboxResponse),
+    Data(serde_json::Value),
+}
+
+impl<'de> Deserialize<'de> for ReplyPayload {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // kind is chosen once the route is known
+        serde_json::Value::deserialize(deserializer).map(ReplyPayload::Data)
+    }
+}
+
+impl EnvelopeData for ReplyData {
+    fn with_route(self, route: &Route) -> Result<Self, Error> {
+        let value = match self.data {
+            ReplyPayload::Data(value) => value,
+            typed => return Ok(ReplyData { data: typed }),
+        };
+        // some fields deserialize from borrowed strings only, which `Value`
+        // doesn't provide
+        let serialized = serde_json::to_vec(&value)?;
+        let data = match route {
+            Route::ReplyKsn(_) => serde_json::from_slice(&serialized).map(ReplyPayload::Ksn),
+            Route::LocScheme => serde_json::from_slice(&serialized).map(ReplyPayload::LocScheme),
+            Route::EndRoleAdd | Route::EndRoleCut => {
+                serde_json::from_slice(&serialized).map(ReplyPayload::EndRole)
+            }
+            Route::ReplyMbx => serde_json::from_slice(&serialized).map(ReplyPayload::Mbx),
+            _ => Ok(ReplyPayload::Data(value)),
+        }
+        .map_err(|e| {
+            Error::DeserializeError(format!("Improper data of {:?} reply: {}", route, e))
+        })?;
+        Ok(ReplyData { data })
+    }
 }
 
 pub type ReplyEvent = SaidEvent<Envelope<ReplyData>>;
@@ -26,12 +80,12 @@ pub type ReplyEvent = SaidEvent<Envelope<ReplyData>>;
 
 impl ReplyEvent {
     pub fn new_reply(
-        ksn: KeyStateNotice,
+        data: ReplyPayload,
         route: Route,
         self_addressing: SelfAddressing,
         serialization: SerializationFormats,
     ) -> Result<EventMessage<ReplyEvent>, Error> {
-        let rpy_data = ReplyData { data: ksn.clone() };
+        let rpy_data = ReplyData { data };
         let env = Envelope::new(route.clone(), rpy_data);
         env.to_message(serialization, &self_addressing)
     }
@@ -40,19 +94,31 @@ impl ReplyEvent {
         self.content.timestamp
     }
 
-    pub fn get_prefix(&self) -> IdentifierPrefix {
-        self.content.data.data.state.prefix.clone()
+    /// Returns identifier which reply data concerns: prefix of key state
+    /// notice, controller of endpoint role or endpoint of location scheme.
+    /// Mailbox messages and application data, whatever its shape, concern
+    /// no identifier.
+    pub fn get_prefix(&self) -> Option<IdentifierPrefix> {
+        match (&self.content.route, &self.content.data.data) {
+            (Route::Custom(_), _) | (_, ReplyPayload::Data(_)) | (_, ReplyPayload::Mbx(_)) => None,
+            (_, ReplyPayload::Ksn(ksn)) => Some(ksn.state.prefix.clone()),
+            (_, ReplyPayload::EndRole(end_role)) => Some(end_role.cid.clone()),
+            (_, ReplyPayload::LocScheme(loc_scheme)) => Some(loc_scheme.eid.clone()),
+        }
     }
 
-    pub fn get_state(&self) -> IdentifierState {
-        self.content.data.data.state.clone()
+    pub fn get_state(&self) -> Result<IdentifierState, Error> {
+        match &self.content.data.data {
+            ReplyPayload::Ksn(ksn) => Ok(ksn.state.clone()),
+            _ => Err(Error::SemanticError("Reply is not key state notice".into())),
+        }
     }
 
     pub fn get_route(&self) -> Route {
         self.content.route.clone()
     }
 
-    pub fn get_reply_data(&self) -> KeyStateNotice {
+    pub fn get_reply_data(&self) -> ReplyPayload {
         self.content.data.data.clone()
     }
 }
@@ -86,6 +152,15 @@ pub struct SignedReply {
 }
 
 impl SignedReply {
+    /// Returns identifier the reply is kept under: the one its data
+    /// concerns or, for application data, the signer.
+    pub fn get_prefix(&self) -> IdentifierPrefix {
+        self.reply
+            .event
+            .get_prefix()
+            .unwrap_or_else(|| self.signature.get_signer())
+    }
+
     pub fn new_nontrans(
         envelope: EventMessage<ReplyEvent>,
         signer: BasicPrefix,
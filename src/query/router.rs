@@ -0,0 +1,96 @@
+use serde::de::DeserializeOwned;
+
+use super::{exchange::SignedExchange, reply::SignedReply, Route};
+use crate::error::Error;
+
+/// Message with application route, which can be passed to handler
+/// registered for the route.
+pub trait Routable {
+    fn get_route(&self) -> Route;
+    /// Data passed to handler.
+    fn get_payload(&self) -> Result<serde_json::Value, Error>;
+}
+
+impl Routable for SignedReply {
+    fn get_route(&self) -> Route {
+        self.reply.event.get_route()
+    }
+
+    fn get_payload(&self) -> Result<serde_json::Value, Error> {
+        Ok(serde_json::to_value(self.reply.event.get_reply_data())?)
+    }
+}
+
+impl Routable for SignedExchange {
+    fn get_route(&self) -> Route {
+        self.exchange.event.get_route()
+    }
+
+    fn get_payload(&self) -> Result<serde_json::Value, Error> {
+        Ok(self.exchange.event.get_data())
+    }
+}
+
+type Handler<M> = Box<dyn Fn(&M) -> Result<(), Error> + Send + Sync>;
+
+/// Router
+///
+/// Dispatches messages of application routes to handlers registered for
+/// them. Replies of KERI routes (`/ksn`, `/loc/scheme`, `/end/role`) are
+/// processed by `EventProcessor` itself.
+pub struct Router<M> {
+    handlers: Vec<(String, Handler<M>)>,
+}
+
+pub type ReplyRouter = Router<SignedReply>;
+pub type ExchangeRouter = Router<SignedExchange>;
+
+impl<M> Default for Router<M> {
+    fn default() -> Self {
+        Self { handlers: vec![] }
+    }
+}
+
+impl<M: Routable> Router<M> {
+    /// Registers `handler` of messages with `route`, replacing previous
+    /// one. Message payload is deserialized into `P` before it's passed to
+    /// handler. Routes of KERI messages can't be registered.
+    pub fn register<P, F>(&mut self, route: &str, handler: F) -> Result<(), Error>
+    where
+        P: DeserializeOwned,
+        F: Fn(&M, P) -> Result<(), Error> + Send + Sync + 'static,
+    {
+        Route::custom(route)?;
+        let handler = move |msg: &M| {
+            let payload = serde_json::from_value(msg.get_payload()?)
+                .map_err(|e| Error::DeserializeError(e.to_string()))?;
+            handler(msg, payload)
+        };
+        self.handlers.retain(|(registered, _)| registered != route);
+        self.handlers.push((route.to_string(), Box::new(handler)));
+        Ok(())
+    }
+
+    pub fn has_handler(&self, route: &Route) -> bool {
+        self.get_handler(route).is_some()
+    }
+
+    /// Passes message to handler registered for its route.
+    pub fn dispatch(&self, msg: &M) -> Result<(), Error> {
+        let handler = self
+            .get_handler(&msg.get_route())
+            .ok_or_else(|| Error::SemanticError("No handler for route".into()))?;
+        handler(msg)
+    }
+
+    fn get_handler(&self, route: &Route) -> Option<&Handler<M>> {
+        match route {
+            Route::Custom(route) => self
+                .handlers
+                .iter()
+                .find(|(registered, _)| registered == route)
+                .map(|(_, handler)| handler),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use super::KeyManager;
+use crate::{
+    derivation::{basic::Basic, self_signing::SelfSigning},
+    error::Error,
+    event::sections::threshold::SignatureThreshold,
+    keys::{PrivateKey, PublicKey},
+    prefix::{AttachedSignaturePrefix, BasicPrefix, SeedPrefix},
+};
+use rand::{rngs::OsRng, RngCore};
+
+struct KeyEntry {
+    derivation: Basic,
+    pub_key: PublicKey,
+    priv_key: PrivateKey,
+}
+
+impl KeyEntry {
+    fn generate(derivation: Basic) -> Result<Self, Error> {
+        let mut seed = vec![0u8; 32];
+        OsRng.fill_bytes(&mut seed);
+        let seed = match derivation {
+            Basic::Ed25519 | Basic::Ed25519NT => SeedPrefix::RandomSeed256Ed25519(seed),
+            Basic::ECDSAsecp256k1 | Basic::ECDSAsecp256k1NT => {
+                SeedPrefix::RandomSeed256ECDSAsecp256k1(seed)
+            }
+            _ => return Err(Error::ImproperPrefixType),
+        };
+        let (pub_key, priv_key) = seed.derive_key_pair()?;
+        Ok(Self {
+            derivation,
+            pub_key,
+            priv_key,
+        })
+    }
+
+    fn sign(&self, msg: &[u8]) -> Result<(SelfSigning, Vec<u8>), Error> {
+        match self.derivation {
+            Basic::Ed25519 | Basic::Ed25519NT => {
+                Ok((SelfSigning::Ed25519Sha512, self.priv_key.sign_ed(msg)?))
+            }
+            Basic::ECDSAsecp256k1 | Basic::ECDSAsecp256k1NT => Ok((
+                SelfSigning::ECDSAsecp256k1Sha256,
+                self.priv_key.sign_ecdsa(msg)?,
+            )),
+            _ => Err(Error::ImproperPrefixType),
+        }
+    }
+}
+
+/// Key Ring
+///
+/// Key manager holding several current and next key pairs, each with its own
+/// derivation type, together with signing thresholds of both sets. Keys are
+/// listed in establishment events in the order given at creation, so
+/// signature indexes match positions in `public_keys`.
+pub struct KeyRing {
+    current: Vec<KeyEntry>,
+    next: Vec<KeyEntry>,
+    threshold: SignatureThreshold,
+    next_threshold: SignatureThreshold,
+}
+
+impl KeyRing {
+    /// Generates random current and next keys of given derivation types.
+    /// Every rotation produces next keys of the same types and threshold.
+    pub fn new(
+        current: &[Basic],
+        threshold: SignatureThreshold,
+        next: &[Basic],
+        next_threshold: SignatureThreshold,
+    ) -> Result<Self, Error> {
+        if current.is_empty() || next.is_empty() {
+            return Err(Error::SemanticError("Empty key set".into()));
+        }
+        Ok(Self {
+            current: current
+                .iter()
+                .map(|d| KeyEntry::generate(*d))
+                .collect::<Result<_, _>>()?,
+            next: next
+                .iter()
+                .map(|d| KeyEntry::generate(*d))
+                .collect::<Result<_, _>>()?,
+            threshold,
+            next_threshold,
+        })
+    }
+}
+
+impl KeyManager for KeyRing {
+    fn sign(&self, msg: &[u8]) -> Result<Vec<u8>, Error> {
+        Ok(self.current[0].sign(msg)?.1)
+    }
+
+    fn public_key(&self) -> Result<PublicKey, Error> {
+        Ok(self.current[0].pub_key.clone())
+    }
+
+    fn next_public_key(&self) -> Result<PublicKey, Error> {
+        Ok(self.next[0].pub_key.clone())
+    }
+
+    fn rotate(&mut self) -> Result<(), Error> {
+        let next = self
+            .next
+            .iter()
+            .map(|entry| KeyEntry::generate(entry.derivation))
+            .collect::<Result<_, _>>()?;
+        self.current = std::mem::replace(&mut self.next, next);
+        self.threshold = self.next_threshold.clone();
+        Ok(())
+    }
+
+    /// Keys are labeled with their own derivations, whatever is requested.
+    fn supports(&self, _derivation: Basic) -> bool {
+        true
+    }
+
+    fn public_keys(&self, _derivation: Basic) -> Result<Vec<BasicPrefix>, Error> {
+        Ok(self
+            .current
+            .iter()
+            .map(|entry| entry.derivation.derive(entry.pub_key.clone()))
+            .collect())
+    }
+
+    fn next_public_keys(&self, _derivation: Basic) -> Result<Vec<BasicPrefix>, Error> {
+        Ok(self
+            .next
+            .iter()
+            .map(|entry| entry.derivation.derive(entry.pub_key.clone()))
+            .collect())
+    }
+
+    fn threshold(&self) -> SignatureThreshold {
+        self.threshold.clone()
+    }
+
+    fn next_threshold(&self) -> SignatureThreshold {
+        self.next_threshold.clone()
+    }
+
+    fn sign_indexed(
+        &self,
+        msg: &[u8],
+        _signature: SelfSigning,
+    ) -> Result<Vec<AttachedSignaturePrefix>, Error> {
+        self.current
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                let (code, signature) = entry.sign(msg)?;
+                Ok(AttachedSignaturePrefix::new(code, signature, index as u16))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,223 @@
+use std::path::Path;
+
+use super::{generate_key_pair, salty::Tier, KeyManager};
+use crate::{
+    error::Error,
+    keys::{PrivateKey, PublicKey},
+};
+use argon2::{Algorithm, Argon2, Version};
+use chacha20poly1305::{
+    aead::{Aead, NewAead},
+    Key, XChaCha20Poly1305, XNonce,
+};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, Zeroizing};
+
+/// Encrypted record kept in sled tree under identifier key
+#[derive(Serialize, Deserialize)]
+struct EncryptedKeys {
+    tier: Tier,
+    salt: Vec<u8>,
+    nonce: Vec<u8>,
+    ciphertext: Vec<u8>,
+}
+
+/// Plaintext content of `EncryptedKeys`
+#[derive(Serialize, Deserialize)]
+struct StoredKeys {
+    current: Vec<u8>,
+    next: Vec<u8>,
+}
+
+impl Drop for StoredKeys {
+    fn drop(&mut self) {
+        self.current.zeroize();
+        self.next.zeroize();
+    }
+}
+
+/// Key Store
+///
+/// Persistent `KeyManager` keeping current and next private keys of an
+/// identifier in a sled tree, encrypted with XChaCha20Poly1305 under a key
+/// stretched from a passphrase. Both keys are written as one record, so
+/// rotation either advances both of them or none.
+pub struct KeyStore {
+    tree: sled::Tree,
+    id: String,
+    tier: Tier,
+    salt: Vec<u8>,
+    encryption_key: Zeroizing<Vec<u8>>,
+    pub_key: PublicKey,
+    priv_key: PrivateKey,
+    next_pub_key: PublicKey,
+    next_priv_key: PrivateKey,
+}
+
+impl KeyStore {
+    /// Generates fresh key pairs for `id` and stores them encrypted under `passphrase`.
+    pub fn create(path: &Path, id: &str, passphrase: &str, tier: Tier) -> Result<Self, Error> {
+        let tree = sled::open(path)?.open_tree(b"keys")?;
+        if tree.contains_key(id)? {
+            return Err(Error::KeyStoreError(format!(
+                "keys of {} already stored",
+                id
+            )));
+        }
+        let mut salt = vec![0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let encryption_key = stretch(passphrase, &salt, tier)?;
+        let (pub_key, priv_key) = generate_key_pair()?;
+        let (next_pub_key, next_priv_key) = generate_key_pair()?;
+        let ks = Self {
+            tree,
+            id: id.to_string(),
+            tier,
+            salt,
+            encryption_key,
+            pub_key,
+            priv_key,
+            next_pub_key,
+            next_priv_key,
+        };
+        ks.store(&ks.priv_key, &ks.next_priv_key)?;
+        Ok(ks)
+    }
+
+    /// Loads and decrypts keys of `id` stored previously with `create`.
+    pub fn open(path: &Path, id: &str, passphrase: &str) -> Result<Self, Error> {
+        let tree = sled::open(path)?.open_tree(b"keys")?;
+        let record: EncryptedKeys = match tree.get(id)? {
+            Some(record) => serde_cbor::from_slice(&record)?,
+            None => return Err(Error::KeyStoreError(format!("no keys of {} stored", id))),
+        };
+        let encryption_key = stretch(passphrase, &record.salt, record.tier)?;
+        let plaintext = Zeroizing::new(
+            XChaCha20Poly1305::new(Key::from_slice(&encryption_key))
+                .decrypt(
+                    XNonce::from_slice(&record.nonce),
+                    record.ciphertext.as_ref(),
+                )
+                .map_err(|_| Error::KeyStoreError("wrong passphrase".into()))?,
+        );
+        let stored: StoredKeys = serde_cbor::from_slice(&plaintext)?;
+        let (pub_key, priv_key) = key_pair(&stored.current)?;
+        let (next_pub_key, next_priv_key) = key_pair(&stored.next)?;
+        Ok(Self {
+            tree,
+            id: id.to_string(),
+            tier: record.tier,
+            salt: record.salt,
+            encryption_key,
+            pub_key,
+            priv_key,
+            next_pub_key,
+            next_priv_key,
+        })
+    }
+
+    fn store(&self, current: &PrivateKey, next: &PrivateKey) -> Result<(), Error> {
+        let plaintext = Zeroizing::new(serde_cbor::to_vec(&StoredKeys {
+            current: current.key(),
+            next: next.key(),
+        })?);
+        let mut nonce = vec![0u8; 24];
+        OsRng.fill_bytes(&mut nonce);
+        let ciphertext = XChaCha20Poly1305::new(Key::from_slice(&self.encryption_key))
+            .encrypt(XNonce::from_slice(&nonce), plaintext.as_ref())
+            .map_err(|_| Error::KeyStoreError("encryption failed".into()))?;
+        let record = EncryptedKeys {
+            tier: self.tier,
+            salt: self.salt.clone(),
+            nonce,
+            ciphertext,
+        };
+        self.tree.insert(&self.id, serde_cbor::to_vec(&record)?)?;
+        self.tree.flush()?;
+        Ok(())
+    }
+}
+
+fn key_pair(secret: &[u8]) -> Result<(PublicKey, PrivateKey), Error> {
+    let sk = ed25519_dalek::SecretKey::from_bytes(secret)?;
+    let pk = ed25519_dalek::PublicKey::from(&sk);
+    Ok((
+        PublicKey::new(pk.to_bytes().to_vec()),
+        PrivateKey::new(secret.to_vec()),
+    ))
+}
+
+fn stretch(passphrase: &str, salt: &[u8], tier: Tier) -> Result<Zeroizing<Vec<u8>>, Error> {
+    let mut key = Zeroizing::new(vec![0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, tier.params()?)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| Error::KeyDerivationError(e.to_string()))?;
+    Ok(key)
+}
+
+impl KeyManager for KeyStore {
+    fn sign(&self, msg: &[u8]) -> Result<Vec<u8>, Error> {
+        self.priv_key.sign_ed(msg)
+    }
+
+    fn public_key(&self) -> Result<PublicKey, Error> {
+        Ok(self.pub_key.clone())
+    }
+
+    fn next_public_key(&self) -> Result<PublicKey, Error> {
+        Ok(self.next_pub_key.clone())
+    }
+
+    fn rotate(&mut self) -> Result<(), Error> {
+        let (next_pub_key, next_priv_key) = generate_key_pair()?;
+        // persist first, so a crash never leaves keys advanced only in memory
+        self.store(&self.next_priv_key, &next_priv_key)?;
+        self.pub_key = std::mem::replace(&mut self.next_pub_key, next_pub_key);
+        self.priv_key = std::mem::replace(&mut self.next_priv_key, next_priv_key);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_keystore_persistence() -> Result<(), Error> {
+    use tempfile::Builder;
+
+    let root = Builder::new().prefix("test-keys").tempdir().unwrap();
+
+    let (current, next) = {
+        let mut ks = KeyStore::create(root.path(), "alice", "secret", Tier::Temp)?;
+        ks.rotate()?;
+        (ks.public_key()?, ks.next_public_key()?)
+    };
+    assert!(matches!(
+        KeyStore::create(root.path(), "alice", "secret", Tier::Temp),
+        Err(Error::KeyStoreError(_))
+    ));
+
+    // restarted process regains keys advanced by rotation
+    let mut ks = KeyStore::open(root.path(), "alice", "secret")?;
+    assert_eq!(ks.public_key()?, current);
+    assert_eq!(ks.next_public_key()?, next);
+    let sig = ks.sign(b"message")?;
+    assert!(current.verify_ed(b"message", &sig));
+
+    ks.rotate()?;
+    assert_eq!(ks.public_key()?, next);
+    drop(ks);
+
+    assert!(matches!(
+        KeyStore::open(root.path(), "alice", "wrong"),
+        Err(Error::KeyStoreError(_))
+    ));
+    assert!(matches!(
+        KeyStore::open(root.path(), "bob", "secret"),
+        Err(Error::KeyStoreError(_))
+    ));
+    assert_eq!(
+        KeyStore::open(root.path(), "alice", "secret")?.public_key()?,
+        next
+    );
+
+    Ok(())
+}
@@ -1,9 +1,20 @@
 use crate::{
+    derivation::{basic::Basic, self_signing::SelfSigning},
     error::Error,
+    event::sections::threshold::SignatureThreshold,
     keys::{PrivateKey, PublicKey},
+    prefix::{AttachedSignaturePrefix, BasicPrefix},
 };
 use rand::rngs::OsRng;
 
+pub mod key_ring;
+#[cfg(feature = "sled-db")]
+pub mod keystore;
+#[cfg(feature = "pkcs11")]
+pub mod pkcs11;
+#[cfg(feature = "async")]
+pub mod remote;
+pub mod salty;
 #[cfg(feature = "wallet")]
 pub mod wallet;
 
@@ -12,6 +23,52 @@ pub trait KeyManager {
     fn public_key(&self) -> Result<PublicKey, Error>;
     fn next_public_key(&self) -> Result<PublicKey, Error>;
     fn rotate(&mut self) -> Result<(), Error>;
+
+    /// Checks if keys of `derivation` can be produced. By default only
+    /// Ed25519 ones, which are the key pairs held by plain managers.
+    fn supports(&self, derivation: Basic) -> bool {
+        matches!(derivation, Basic::Ed25519 | Basic::Ed25519NT)
+    }
+
+    /// Current keys in the order they are listed in establishment events.
+    /// By default single key of requested `derivation`, if supported.
+    /// Managers aware of their key types may ignore it.
+    fn public_keys(&self, derivation: Basic) -> Result<Vec<BasicPrefix>, Error> {
+        if !self.supports(derivation) {
+            return Err(Error::SemanticError("Unsupported key derivation".into()));
+        }
+        Ok(vec![derivation.derive(self.public_key()?)])
+    }
+
+    /// Keys committed to as next keys. Single key by default.
+    fn next_public_keys(&self, derivation: Basic) -> Result<Vec<BasicPrefix>, Error> {
+        if !self.supports(derivation) {
+            return Err(Error::SemanticError("Unsupported key derivation".into()));
+        }
+        Ok(vec![derivation.derive(self.next_public_key()?)])
+    }
+
+    fn threshold(&self) -> SignatureThreshold {
+        SignatureThreshold::default()
+    }
+
+    fn next_threshold(&self) -> SignatureThreshold {
+        SignatureThreshold::default()
+    }
+
+    /// Signs `msg` with current keys. Index of each signature is the
+    /// position of its key in `public_keys`.
+    fn sign_indexed(
+        &self,
+        msg: &[u8],
+        signature: SelfSigning,
+    ) -> Result<Vec<AttachedSignaturePrefix>, Error> {
+        Ok(vec![AttachedSignaturePrefix::new(
+            signature,
+            self.sign(msg)?,
+            0,
+        )])
+    }
 }
 
 pub struct CryptoBox {
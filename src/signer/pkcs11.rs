@@ -0,0 +1,290 @@
+use std::{convert::TryFrom, path::Path};
+
+use super::KeyManager;
+use crate::{
+    derivation::{basic::Basic, self_signing::SelfSigning},
+    error::Error,
+    keys::PublicKey,
+    prefix::{AttachedSignaturePrefix, BasicPrefix},
+};
+use cryptoki::{
+    context::{CInitializeArgs, Pkcs11},
+    mechanism::Mechanism,
+    object::{Attribute, AttributeType, KeyType, ObjectClass, ObjectHandle},
+    session::{Session, UserType},
+};
+use k256::ecdsa::{Signature, VerifyingKey};
+use sha2::{Digest, Sha256};
+
+/// DER encoded OID of secp256k1 curve
+const SECP256K1_PARAMS: [u8; 7] = [0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x0a];
+
+struct TokenKey {
+    id: u64,
+    private: ObjectHandle,
+    public: ObjectHandle,
+    pub_key: PublicKey,
+}
+
+/// PKCS#11 Key Manager
+///
+/// Keeps secp256k1 key pairs on a PKCS#11 token as non-extractable objects,
+/// so private keys never leave the token. Key pairs of an identifier share
+/// `label` and are ordered by `Id` attribute, which holds the index of the
+/// establishment event introducing them.
+pub struct Pkcs11KeyManager {
+    session: Session,
+    label: String,
+    current: TokenKey,
+    next: TokenKey,
+}
+
+impl Pkcs11KeyManager {
+    /// Generates current and next key pairs on the first initialized token
+    /// available through `module`.
+    pub fn create(module: &Path, pin: &str, label: &str) -> Result<Self, Error> {
+        let session = open_session(module, pin)?;
+        if !find_keys(&session, label)?.is_empty() {
+            return Err(Error::KeyStoreError(format!(
+                "keys labeled {} already on token",
+                label
+            )));
+        }
+        let current = generate_key(&session, label, 0)?;
+        let next = generate_key(&session, label, 1)?;
+        Ok(Self {
+            session,
+            label: label.to_string(),
+            current,
+            next,
+        })
+    }
+
+    /// Finds key pairs generated previously with `create`. Current and next
+    /// keys are the two with the highest ids. Older keys, left over by
+    /// rotation interrupted before destroying them, are destroyed.
+    pub fn open(module: &Path, pin: &str, label: &str) -> Result<Self, Error> {
+        let session = open_session(module, pin)?;
+        let mut keys = find_keys(&session, label)?;
+        if keys.len() < 2 {
+            return Err(Error::KeyStoreError(format!(
+                "expected current and next keys labeled {}, found {}",
+                label,
+                keys.len()
+            )));
+        }
+        keys.sort_by_key(|key| key.id);
+        let next = keys.pop().unwrap();
+        let current = keys.pop().unwrap();
+        for old in keys {
+            destroy_key(&session, old)?;
+        }
+        Ok(Self {
+            session,
+            label: label.to_string(),
+            current,
+            next,
+        })
+    }
+}
+
+impl KeyManager for Pkcs11KeyManager {
+    fn sign(&self, msg: &[u8]) -> Result<Vec<u8>, Error> {
+        // CKM_ECDSA signs a precomputed digest
+        let digest = Sha256::digest(msg);
+        let signature = self
+            .session
+            .sign(&Mechanism::Ecdsa, self.current.private, &digest)?;
+        let mut signature = Signature::try_from(signature.as_slice())
+            .map_err(|e| Error::SemanticError(e.to_string()))?;
+        // tokens don't normalize, while verification requires low S
+        signature
+            .normalize_s()
+            .map_err(|e| Error::SemanticError(e.to_string()))?;
+        Ok(signature.as_ref().to_vec())
+    }
+
+    fn public_key(&self) -> Result<PublicKey, Error> {
+        Ok(self.current.pub_key.clone())
+    }
+
+    fn next_public_key(&self) -> Result<PublicKey, Error> {
+        Ok(self.next.pub_key.clone())
+    }
+
+    fn rotate(&mut self) -> Result<(), Error> {
+        // new key with the highest id makes rotation persisted on token, so
+        // old key is destroyed last and `open` cleans it up if we crash
+        let next = generate_key(&self.session, &self.label, self.next.id + 1)?;
+        let old = std::mem::replace(&mut self.current, std::mem::replace(&mut self.next, next));
+        destroy_key(&self.session, old)
+    }
+
+    fn supports(&self, derivation: Basic) -> bool {
+        matches!(derivation, Basic::ECDSAsecp256k1 | Basic::ECDSAsecp256k1NT)
+    }
+
+    fn public_keys(&self, _derivation: Basic) -> Result<Vec<BasicPrefix>, Error> {
+        Ok(vec![Basic::ECDSAsecp256k1.derive(self.public_key()?)])
+    }
+
+    fn next_public_keys(&self, _derivation: Basic) -> Result<Vec<BasicPrefix>, Error> {
+        Ok(vec![Basic::ECDSAsecp256k1.derive(self.next_public_key()?)])
+    }
+
+    fn sign_indexed(
+        &self,
+        msg: &[u8],
+        _signature: SelfSigning,
+    ) -> Result<Vec<AttachedSignaturePrefix>, Error> {
+        Ok(vec![AttachedSignaturePrefix::new(
+            SelfSigning::ECDSAsecp256k1Sha256,
+            self.sign(msg)?,
+            0,
+        )])
+    }
+}
+
+fn open_session(module: &Path, pin: &str) -> Result<Session, Error> {
+    let mut pkcs11 = Pkcs11::new(module)?;
+    pkcs11.initialize(CInitializeArgs::OsThreads)?;
+    let slot = *pkcs11
+        .get_slots_with_initialized_token()?
+        .first()
+        .ok_or_else(|| Error::KeyStoreError("no initialized token".into()))?;
+    let session = pkcs11.open_rw_session(slot)?;
+    session.login(UserType::User, Some(pin))?;
+    Ok(session)
+}
+
+fn generate_key(session: &Session, label: &str, id: u64) -> Result<TokenKey, Error> {
+    let common = vec![
+        Attribute::Token(true),
+        Attribute::Label(label.as_bytes().to_vec()),
+        Attribute::Id(id.to_be_bytes().to_vec()),
+    ];
+    let public_template = [
+        common.clone(),
+        vec![
+            Attribute::EcParams(SECP256K1_PARAMS.to_vec()),
+            Attribute::Verify(true),
+        ],
+    ]
+    .concat();
+    let private_template = [
+        common,
+        vec![
+            Attribute::Private(true),
+            Attribute::Sensitive(true),
+            Attribute::Extractable(false),
+            Attribute::Sign(true),
+        ],
+    ]
+    .concat();
+    let (public, private) = session.generate_key_pair(
+        &Mechanism::EccKeyPairGen,
+        &public_template,
+        &private_template,
+    )?;
+    Ok(TokenKey {
+        id,
+        private,
+        public,
+        pub_key: read_public_key(session, public)?,
+    })
+}
+
+/// Destroys private key first, as `find_keys` looks keys up by private
+/// ones and requires their public counterparts.
+fn destroy_key(session: &Session, key: TokenKey) -> Result<(), Error> {
+    session.destroy_object(key.private)?;
+    session.destroy_object(key.public)?;
+    Ok(())
+}
+
+fn find_keys(session: &Session, label: &str) -> Result<Vec<TokenKey>, Error> {
+    session
+        .find_objects(&[
+            Attribute::Class(ObjectClass::PRIVATE_KEY),
+            Attribute::KeyType(KeyType::EC),
+            Attribute::Label(label.as_bytes().to_vec()),
+        ])?
+        .into_iter()
+        .map(|private| {
+            let id = match session.get_attributes(private, &[AttributeType::Id])?.pop() {
+                Some(Attribute::Id(id)) if id.len() == 8 => {
+                    let mut bytes = [0u8; 8];
+                    bytes.copy_from_slice(&id);
+                    u64::from_be_bytes(bytes)
+                }
+                _ => return Err(Error::KeyStoreError("improper key id".into())),
+            };
+            let public = *session
+                .find_objects(&[
+                    Attribute::Class(ObjectClass::PUBLIC_KEY),
+                    Attribute::Label(label.as_bytes().to_vec()),
+                    Attribute::Id(id.to_be_bytes().to_vec()),
+                ])?
+                .first()
+                .ok_or_else(|| Error::KeyStoreError("missing public key".into()))?;
+            Ok(TokenKey {
+                id,
+                private,
+                public,
+                pub_key: read_public_key(session, public)?,
+            })
+        })
+        .collect()
+}
+
+fn read_public_key(session: &Session, public: ObjectHandle) -> Result<PublicKey, Error> {
+    let point = match session
+        .get_attributes(public, &[AttributeType::EcPoint])?
+        .pop()
+    {
+        Some(Attribute::EcPoint(point)) => point,
+        _ => return Err(Error::PublicKeyError("missing EC point".into())),
+    };
+    // EC point is DER encoded octet string of uncompressed SEC1 point
+    let sec1 = match point.as_slice() {
+        [0x04, len, sec1 @ ..] if *len as usize == sec1.len() => sec1,
+        _ => return Err(Error::PublicKeyError("improper EC point encoding".into())),
+    };
+    let key =
+        VerifyingKey::from_sec1_bytes(sec1).map_err(|e| Error::PublicKeyError(e.to_string()))?;
+    Ok(PublicKey::new(key.to_bytes().to_vec()))
+}
+
+/// Requires SoftHSM (or other PKCS#11 module) with initialized token.
+/// Module path and user PIN are taken from `PKCS11_MODULE` and `PKCS11_PIN`.
+#[test]
+#[ignore]
+fn test_pkcs11_key_manager() -> Result<(), Error> {
+    let module = std::env::var("PKCS11_MODULE").expect("PKCS11_MODULE not set");
+    let pin = std::env::var("PKCS11_PIN").expect("PKCS11_PIN not set");
+    let label = format!("keri-test-{}", rand::random::<u32>());
+
+    let mut km = Pkcs11KeyManager::create(Path::new(&module), &pin, &label)?;
+    let sig = km.sign(b"message")?;
+    assert!(km.public_key()?.verify_ecdsa(b"message", &sig));
+
+    let next = km.next_public_key()?;
+    km.rotate()?;
+    assert_eq!(km.public_key()?, next);
+    drop(km);
+
+    let km = Pkcs11KeyManager::open(Path::new(&module), &pin, &label)?;
+    assert_eq!(km.public_key()?, next);
+    let sig = km.sign(b"message")?;
+    assert!(next.verify_ecdsa(b"message", &sig));
+
+    // rotation interrupted before old key was destroyed
+    let next = km.next_public_key()?;
+    generate_key(&km.session, &label, km.next.id + 1)?;
+    drop(km);
+    let km = Pkcs11KeyManager::open(Path::new(&module), &pin, &label)?;
+    assert_eq!(km.public_key()?, next);
+    assert_eq!(find_keys(&km.session, &label)?.len(), 2);
+
+    Ok(())
+}
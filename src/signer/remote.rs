@@ -0,0 +1,271 @@
+use std::{future::Future, pin::Pin};
+
+use super::KeyManager;
+use crate::{
+    derivation::{basic::Basic, self_signing::SelfSigning},
+    error::Error,
+    event::sections::threshold::SignatureThreshold,
+    prefix::{AttachedSignaturePrefix, BasicPrefix},
+};
+use serde::{Deserialize, Serialize};
+
+/// Boxed future returned by `AsyncKeyManager`. It doesn't borrow the
+/// signer, so no lock has to be held while the answer is awaited.
+pub type SignerFuture<T> = Pin<Box<dyn Future<Output = Result<T, Error>> + Send + 'static>>;
+
+/// Public part of controller key configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PublicKeys {
+    pub current: Vec<BasicPrefix>,
+    pub threshold: SignatureThreshold,
+    pub next: Vec<BasicPrefix>,
+    pub next_threshold: SignatureThreshold,
+}
+
+impl PublicKeys {
+    pub fn from_key_manager<K: KeyManager>(km: &K, derivation: Basic) -> Result<Self, Error> {
+        Ok(Self {
+            current: km.public_keys(derivation)?,
+            threshold: km.threshold(),
+            next: km.next_public_keys(derivation)?,
+            next_threshold: km.next_threshold(),
+        })
+    }
+}
+
+/// Asynchronous Key Manager
+///
+/// Signer whose requests may be fulfilled out of process, eg. by a separate
+/// signing service or after approval of the user.
+pub trait AsyncKeyManager {
+    /// Keys of requested `derivation`, as in `KeyManager::public_keys`.
+    fn public_keys(&self, derivation: Basic) -> SignerFuture<PublicKeys>;
+    /// Signs `msg` with current keys, returning signatures indexed by
+    /// position of their keys in `PublicKeys::current`.
+    fn sign(
+        &self,
+        msg: &[u8],
+        signature: SelfSigning,
+    ) -> SignerFuture<Vec<AttachedSignaturePrefix>>;
+    fn rotate(&mut self) -> SignerFuture<()>;
+}
+
+/// Request of the signing daemon protocol. Every request and response is
+/// a single line of JSON.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum Request {
+    Keys { derivation: Basic },
+    Sign { msg: String, signature: String },
+    Rotate,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Response<T> {
+    Ok(T),
+    Error(String),
+}
+
+#[cfg(unix)]
+pub use unix::{RemoteKeyManager, SigningDaemon};
+
+#[cfg(unix)]
+mod unix {
+    use std::{
+        path::{Path, PathBuf},
+        sync::{Arc, Mutex},
+    };
+
+    use super::{AsyncKeyManager, PublicKeys, Request, Response, SignerFuture};
+    use crate::{
+        derivation::{basic::Basic, self_signing::SelfSigning, DerivationCode},
+        error::Error,
+        prefix::AttachedSignaturePrefix,
+        signer::KeyManager,
+        transport,
+    };
+    use async_std::{
+        io::{prelude::BufReadExt, BufReader, WriteExt},
+        os::unix::net::{UnixListener, UnixStream},
+        stream::StreamExt,
+    };
+    use serde::{de::DeserializeOwned, Serialize};
+
+    fn io_error(e: std::io::Error) -> Error {
+        Error::RemoteSignerError(e.to_string())
+    }
+
+    /// Signing Daemon
+    ///
+    /// Serves keys of wrapped `KeyManager` over a Unix socket, so they can be
+    /// kept in a process separate from the application.
+    pub struct SigningDaemon<K: KeyManager> {
+        listener: UnixListener,
+        key_manager: Arc<Mutex<K>>,
+    }
+
+    impl<K: KeyManager + Send + 'static> SigningDaemon<K> {
+        pub async fn bind(path: &Path, key_manager: Arc<Mutex<K>>) -> Result<Self, Error> {
+            let listener = UnixListener::bind(path).await.map_err(io_error)?;
+            Ok(Self {
+                listener,
+                key_manager,
+            })
+        }
+
+        /// Answers signing requests of accepted connections until the
+        /// listener fails.
+        pub async fn run(self) -> Result<(), Error> {
+            let key_manager = self.key_manager;
+            transport::accept(self.listener.incoming(), |stream| {
+                Self::serve(stream, Arc::clone(&key_manager))
+            })
+            .await
+            .map_err(io_error)
+        }
+
+        async fn serve(stream: UnixStream, key_manager: Arc<Mutex<K>>) -> Result<(), Error> {
+            let mut writer = stream.clone();
+            let mut lines = BufReader::new(stream).lines();
+            while let Some(line) = lines.next().await {
+                let line = line.map_err(io_error)?;
+                let response = match serde_json::from_str(&line) {
+                    Ok(request) => Self::handle(request, &key_manager),
+                    Err(e) => serde_json::to_string(&Response::<()>::Error(e.to_string())),
+                }?;
+                writer
+                    .write_all(format!("{}\n", response).as_bytes())
+                    .await
+                    .map_err(io_error)?;
+            }
+            Ok(())
+        }
+
+        fn handle(request: Request, key_manager: &Mutex<K>) -> serde_json::Result<String> {
+            fn respond<T: Serialize>(result: Result<T, Error>) -> serde_json::Result<String> {
+                serde_json::to_string(&match result {
+                    Ok(value) => Response::Ok(value),
+                    Err(e) => Response::Error(e.to_string()),
+                })
+            }
+            let mut km = match key_manager.lock() {
+                Ok(km) => km,
+                Err(_) => return respond::<()>(Err(Error::MutexPoisoned)),
+            };
+            match request {
+                Request::Keys { derivation } => {
+                    respond(PublicKeys::from_key_manager(&*km, derivation))
+                }
+                Request::Sign { msg, signature } => respond(
+                    base64::decode_config(msg, base64::URL_SAFE)
+                        .map_err(Error::from)
+                        .and_then(|msg| km.sign_indexed(&msg, signature.parse()?)),
+                ),
+                Request::Rotate => respond(km.rotate()),
+            }
+        }
+    }
+
+    /// Remote Key Manager
+    ///
+    /// Client of `SigningDaemon`, opening new connection for every request.
+    pub struct RemoteKeyManager {
+        path: PathBuf,
+    }
+
+    impl RemoteKeyManager {
+        pub fn new(path: &Path) -> Self {
+            Self {
+                path: path.to_path_buf(),
+            }
+        }
+
+        fn request<T: DeserializeOwned + Send + 'static>(
+            &self,
+            request: Request,
+        ) -> SignerFuture<T> {
+            let path = self.path.clone();
+            Box::pin(async move {
+                let mut stream = UnixStream::connect(&path).await.map_err(io_error)?;
+                let request = format!("{}\n", serde_json::to_string(&request)?);
+                stream
+                    .write_all(request.as_bytes())
+                    .await
+                    .map_err(io_error)?;
+                let mut response = String::new();
+                BufReader::new(stream)
+                    .read_line(&mut response)
+                    .await
+                    .map_err(io_error)?;
+                match serde_json::from_str(&response)? {
+                    Response::Ok(value) => Ok(value),
+                    Response::Error(e) => Err(Error::RemoteSignerError(e)),
+                }
+            })
+        }
+    }
+
+    impl AsyncKeyManager for RemoteKeyManager {
+        fn public_keys(&self, derivation: Basic) -> SignerFuture<PublicKeys> {
+            self.request(Request::Keys { derivation })
+        }
+
+        fn sign(
+            &self,
+            msg: &[u8],
+            signature: SelfSigning,
+        ) -> SignerFuture<Vec<AttachedSignaturePrefix>> {
+            self.request(Request::Sign {
+                msg: base64::encode_config(msg, base64::URL_SAFE),
+                signature: signature.to_str(),
+            })
+        }
+
+        fn rotate(&mut self) -> SignerFuture<()> {
+            self.request(Request::Rotate)
+        }
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_remote_signer() -> Result<(), Error> {
+    use crate::{
+        database::sled::SledEventDatabase,
+        keri::Keri,
+        signer::{CryptoBox, KeyManager},
+    };
+    use std::sync::{Arc, Mutex};
+    use tempfile::Builder;
+
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db = Arc::new(SledEventDatabase::new(root.path())?);
+    let socket = root.path().join("signer.sock");
+
+    async_std::task::block_on(async {
+        let key_manager = Arc::new(Mutex::new(CryptoBox::new()?));
+        let daemon = SigningDaemon::bind(&socket, Arc::clone(&key_manager)).await?;
+        async_std::task::spawn(async move { daemon.run().await.ok() });
+
+        let signer = RemoteKeyManager::new(&socket);
+        let mut keri = Keri::new(db, Arc::new(Mutex::new(signer)))?;
+
+        keri.incept_async(None).await?;
+        keri.make_ixn_async(Some("data")).await?;
+        let next = key_manager.lock().unwrap().next_public_key()?;
+        keri.rotate_async().await?;
+
+        let state = keri.get_state()?.unwrap();
+        assert_eq!(state.sn, 2);
+        assert_eq!(state.current.public_keys[0].public_key, next);
+        assert_eq!(
+            state.current.public_keys,
+            key_manager
+                .lock()
+                .unwrap()
+                .public_keys(crate::derivation::basic::Basic::Ed25519)?
+        );
+        Ok(())
+    })
+}
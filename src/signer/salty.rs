@@ -0,0 +1,220 @@
+use super::KeyManager;
+use crate::{
+    error::Error,
+    keys::{PrivateKey, PublicKey},
+    prefix::SeedPrefix,
+};
+use argon2::{Algorithm, Argon2, Params, Version};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+/// Security tier of the memory-hard key stretching
+///
+/// Mirrors the libsodium `crypto_pwhash` interactive, moderate and
+/// sensitive limits used by keripy. `Temp` is cheap and meant for tests only.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Tier {
+    Low,
+    Med,
+    High,
+    Temp,
+}
+
+impl Tier {
+    pub(crate) fn params(&self) -> Result<Params, Error> {
+        // (memory cost in KiB, iterations)
+        let (m_cost, t_cost) = match self {
+            Tier::Low => (64 * 1024, 2),
+            Tier::Med => (256 * 1024, 3),
+            Tier::High => (1024 * 1024, 4),
+            Tier::Temp => (8, 1),
+        };
+        Params::new(m_cost, t_cost, 1, Some(32))
+            .map_err(|e| Error::KeyDerivationError(e.to_string()))
+    }
+}
+
+/// Salty Key Manager
+///
+/// Hierarchical deterministic key manager. Every key pair is stretched with
+/// Argon2id from a 128 bit salt, an optional passcode and a derivation path
+/// `{id}/{stem}/{ridx:x}`, so the whole key sequence can be recreated
+/// from the salt and passcode alone. `id` names the identifier the keys
+/// belong to, chosen by the controller, as its prefix is derived from
+/// the keys. Neither `id` nor `stem` can contain `/`, so paths of
+/// different identifiers sharing the salt never collide.
+pub struct SaltyKeyManager {
+    salt: SeedPrefix,
+    passcode: Zeroizing<Vec<u8>>,
+    id: String,
+    stem: String,
+    tier: Tier,
+    ridx: u64,
+    pub_key: PublicKey,
+    priv_key: PrivateKey,
+    next_pub_key: PublicKey,
+    next_priv_key: PrivateKey,
+}
+
+impl SaltyKeyManager {
+    /// Creates key manager with keys of the inception (rotation index 0).
+    pub fn new(
+        salt: SeedPrefix,
+        passcode: &str,
+        id: &str,
+        stem: &str,
+        tier: Tier,
+    ) -> Result<Self, Error> {
+        Self::at_rotation(salt, passcode, id, stem, tier, 0)
+    }
+
+    /// Recreates key manager with keys established by the `ridx` rotation.
+    /// Used to recover control of identifier after device loss.
+    pub fn at_rotation(
+        salt: SeedPrefix,
+        passcode: &str,
+        id: &str,
+        stem: &str,
+        tier: Tier,
+        ridx: u64,
+    ) -> Result<Self, Error> {
+        if !matches!(salt, SeedPrefix::RandomSeed128(_)) {
+            return Err(Error::ImproperPrefixType);
+        }
+        if id.contains('/') || stem.contains('/') {
+            return Err(Error::KeyDerivationError(
+                "Path segment can't contain /".into(),
+            ));
+        }
+        let mut km = Self {
+            salt,
+            passcode: Zeroizing::new(passcode.as_bytes().to_vec()),
+            id: id.to_string(),
+            stem: stem.to_string(),
+            tier,
+            ridx,
+            pub_key: PublicKey::new(vec![]),
+            priv_key: PrivateKey::new(vec![]),
+            next_pub_key: PublicKey::new(vec![]),
+            next_priv_key: PrivateKey::new(vec![]),
+        };
+        let (pub_key, priv_key) = km.derive_key_pair(ridx)?;
+        let (next_pub_key, next_priv_key) = km.derive_key_pair(ridx + 1)?;
+        km.pub_key = pub_key;
+        km.priv_key = priv_key;
+        km.next_pub_key = next_pub_key;
+        km.next_priv_key = next_priv_key;
+        Ok(km)
+    }
+
+    /// Generates fresh random salt to be stored by the controller.
+    pub fn generate_salt() -> SeedPrefix {
+        let mut salt = vec![0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        SeedPrefix::RandomSeed128(salt)
+    }
+
+    /// Index of rotation which established current keys.
+    pub fn rotation_index(&self) -> u64 {
+        self.ridx
+    }
+
+    fn derive_key_pair(&self, ridx: u64) -> Result<(PublicKey, PrivateKey), Error> {
+        // one key per establishment event, so rotation index identifies key
+        let path = format!("{}/{}/{:x}", self.id, self.stem, ridx);
+        let salt = match &self.salt {
+            SeedPrefix::RandomSeed128(salt) => salt,
+            _ => return Err(Error::ImproperPrefixType),
+        };
+        let argon = if self.passcode.is_empty() {
+            Argon2::new(Algorithm::Argon2id, Version::V0x13, self.tier.params()?)
+        } else {
+            Argon2::new_with_secret(
+                &self.passcode,
+                Algorithm::Argon2id,
+                Version::V0x13,
+                self.tier.params()?,
+            )
+            .map_err(|e| Error::KeyDerivationError(e.to_string()))?
+        };
+        let mut seed = Zeroizing::new(vec![0u8; 32]);
+        argon
+            .hash_password_into(path.as_bytes(), salt, &mut seed)
+            .map_err(|e| Error::KeyDerivationError(e.to_string()))?;
+        SeedPrefix::RandomSeed256Ed25519(seed.to_vec()).derive_key_pair()
+    }
+}
+
+impl KeyManager for SaltyKeyManager {
+    fn sign(&self, msg: &[u8]) -> Result<Vec<u8>, Error> {
+        self.priv_key.sign_ed(msg)
+    }
+
+    fn public_key(&self) -> Result<PublicKey, Error> {
+        Ok(self.pub_key.clone())
+    }
+
+    fn next_public_key(&self) -> Result<PublicKey, Error> {
+        Ok(self.next_pub_key.clone())
+    }
+
+    fn rotate(&mut self) -> Result<(), Error> {
+        let (next_pub_key, next_priv_key) = self.derive_key_pair(self.ridx + 2)?;
+        self.pub_key = std::mem::replace(&mut self.next_pub_key, next_pub_key);
+        self.priv_key = std::mem::replace(&mut self.next_priv_key, next_priv_key);
+        self.ridx += 1;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_salty_key_derivation() -> Result<(), Error> {
+    let salt: SeedPrefix = "0AMDEyMzQ1Njc4OWFiY2RlZg".parse()?;
+
+    let mut km = SaltyKeyManager::new(salt.clone(), "passcode", "alice", "signing", Tier::Temp)?;
+    let restored = SaltyKeyManager::new(salt.clone(), "passcode", "alice", "signing", Tier::Temp)?;
+    assert_eq!(km.public_key()?, restored.public_key()?);
+    assert_eq!(km.next_public_key()?, restored.next_public_key()?);
+
+    // next keys become current keys after rotation
+    let next = km.next_public_key()?;
+    km.rotate()?;
+    assert_eq!(km.rotation_index(), 1);
+    assert_eq!(km.public_key()?, next);
+    let sig = km.sign(b"message")?;
+    assert!(km.public_key()?.verify_ed(b"message", &sig));
+
+    // keys after rotation are recoverable from salt and passcode
+    let recovered =
+        SaltyKeyManager::at_rotation(salt.clone(), "passcode", "alice", "signing", Tier::Temp, 1)?;
+    assert_eq!(km.public_key()?, recovered.public_key()?);
+    assert_eq!(km.next_public_key()?, recovered.next_public_key()?);
+
+    // other passcode, identifier or stem gives unrelated keys
+    let other = SaltyKeyManager::new(salt.clone(), "other", "alice", "signing", Tier::Temp)?;
+    assert_ne!(restored.public_key()?, other.public_key()?);
+    let other = SaltyKeyManager::new(salt.clone(), "passcode", "bob", "signing", Tier::Temp)?;
+    assert_ne!(restored.public_key()?, other.public_key()?);
+    let other = SaltyKeyManager::new(salt.clone(), "passcode", "alice", "other", Tier::Temp)?;
+    assert_ne!(restored.public_key()?, other.public_key()?);
+
+    // stem and rotation index don't run into each other
+    let short = SaltyKeyManager::at_rotation(salt.clone(), "", "alice", "x", Tier::Temp, 0x11)?;
+    let long = SaltyKeyManager::at_rotation(salt.clone(), "", "alice", "x11", Tier::Temp, 1)?;
+    assert_ne!(short.public_key()?, long.public_key()?);
+    assert!(SaltyKeyManager::new(salt, "", "alice/x", "signing", Tier::Temp).is_err());
+
+    assert!(matches!(
+        SaltyKeyManager::new(
+            SeedPrefix::RandomSeed256Ed25519(vec![0; 32]),
+            "",
+            "alice",
+            "signing",
+            Tier::Temp
+        ),
+        Err(Error::ImproperPrefixType)
+    ));
+
+    Ok(())
+}
@@ -1,6 +1,9 @@
 use crate::{
     error::Error,
-    event::{event_data::EventData, sections::KeyConfig},
+    event::{
+        event_data::EventData,
+        sections::{configuration::ConfigurationTrait, KeyConfig},
+    },
     event_message::EventTypeTag,
     prefix::{BasicPrefix, IdentifierPrefix, SelfAddressingPrefix},
 };
@@ -46,9 +49,16 @@ pub struct IdentifierState {
     #[serde(rename = "b")]
     pub witnesses: Vec<BasicPrefix>,
 
-    #[serde(rename = "di")]
+    #[serde(
+        rename = "di",
+        default,
+        deserialize_with = "crate::event::sections::key_config::empty_string_as_none::deserialize"
+    )]
     pub delegator: Option<IdentifierPrefix>,
 
+    #[serde(rename = "c", default)]
+    pub config: Vec<ConfigurationTrait>,
+
     #[serde(rename = "ee")]
     pub last_est: LastEstablishmentData,
 }
@@ -75,6 +85,14 @@ impl From<&EventData> for EventTypeTag {
 }
 
 impl IdentifierState {
+    /// Is Abandoned
+    ///
+    /// Establishment event without next keys commitment is the last event
+    /// of identifier, so no further events can be applied.
+    pub fn is_abandoned(&self) -> bool {
+        self.prefix != IdentifierPrefix::default() && self.current.threshold_key_digest.is_none()
+    }
+
     /// Apply
     ///
     /// validates and applies the semantic rules of the event to the event state
@@ -0,0 +1,32 @@
+use std::future::Future;
+
+use async_std::{
+    io,
+    stream::{Stream, StreamExt},
+    task,
+};
+
+use crate::error::Error;
+
+#[cfg(feature = "query")]
+pub(crate) fn transport_error(e: impl ToString) -> Error {
+    Error::TransportError(e.to_string())
+}
+
+/// Spawns `serve` task for every connection of `incoming` until accepting
+/// connection fails. Errors of single connection are dropped with it, so
+/// they don't stop the listener.
+pub(crate) async fn accept<S, I, F, T>(mut incoming: I, serve: F) -> io::Result<()>
+where
+    I: Stream<Item = io::Result<S>> + Unpin,
+    F: Fn(S) -> T,
+    T: Future<Output = Result<(), Error>> + Send + 'static,
+{
+    while let Some(stream) = incoming.next().await {
+        let connection = serve(stream?);
+        task::spawn(async move {
+            let _ = connection.await;
+        });
+    }
+    Ok(())
+}